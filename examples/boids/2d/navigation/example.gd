@@ -0,0 +1,17 @@
+extends Node2D
+
+# The flock is moved away from the origin, and its target is nested under another node,
+# so the target and the navigation path have to be converted into the space of the flock.
+
+func _ready() -> void:
+	for i in 200: spawnBoid($Flocks/Flock)
+	DebugCam.add_debug_cam(self)
+
+func _unhandled_input(event: InputEvent) -> void:
+	if event is InputEventMouseButton and event.pressed:
+		$Goal/Marker.global_position = get_global_mouse_position()
+
+func spawnBoid(flock: Flock2D) -> void:
+	var boid: Boid2D = preload("../example_boid.tscn").instantiate()
+	flock.add_child(boid)
+	boid.global_position = Vector2(randf_range(60, 460), randf_range(60, 580))
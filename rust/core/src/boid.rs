@@ -0,0 +1,315 @@
+use std::ops::Sub;
+
+use glam::*;
+
+use crate::{BoidProperties, Environment, Flock, FlockProperties, Partition};
+
+/// A single boid.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Boid {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    /// Index of this boid's properties in the `boid_properties` of its flock.
+    pub properties: usize,
+    /// Obstacle repulsion calculated outside of the simulation (eg. from physics raycasts).
+    /// It's added to the repulsion of the environment's obstacles, see `Obstacle::repulsion`.
+    pub external_repulsion: Vec3,
+    /// Point on the wander circle this boid steers towards, relative to the center of the circle.
+    /// See `BoidProperties::wander_radius`.
+    pub wander: Vec3,
+}
+
+impl Boid {
+    pub fn new(position: Vec3, velocity: Vec3, properties: usize) -> Self {
+        Self {
+            position,
+            velocity,
+            properties,
+            external_repulsion: Vec3::ZERO,
+            wander: Vec3::ZERO,
+        }
+    }
+
+    /// Applies a force calculated by `calculate_boid` to this boid for `delta` seconds, then moves it by its new velocity.
+    #[inline(always)]
+    pub fn apply_force(&mut self, properties: &BoidProperties, force: Vec3, delta: f32) {
+        integrate(
+            &mut self.position,
+            &mut self.velocity,
+            properties,
+            force,
+            delta,
+        );
+    }
+}
+
+/// Applies `force` to a boid's `velocity` for `delta` seconds, then moves its `position` by the new velocity.
+/// Drag is applied after the force, then the speed is kept between the min and max speed of the boid.
+#[inline(always)]
+pub fn integrate(
+    position: &mut Vec3,
+    velocity: &mut Vec3,
+    properties: &BoidProperties,
+    force: Vec3,
+    delta: f32,
+) {
+    *velocity += force * delta;
+
+    let speed = velocity.length();
+    if speed > 0.0 {
+        let drag = properties.linear_drag * speed + properties.quadratic_drag * speed * speed;
+        let dragged = (speed - drag * delta).max(0.0);
+        let clamped = dragged.max(properties.min_speed).min(properties.max_speed);
+        *velocity *= clamped / speed;
+    }
+    *position += *velocity * delta;
+}
+
+struct CalcArgs {
+    steer: Vec3,
+    align: Vec3,
+    cohere: Vec3,
+
+    steer_count: i32,
+    align_count: i32,
+    cohere_count: i32,
+}
+
+impl CalcArgs {
+    const fn identity() -> Self {
+        Self {
+            steer: Vec3::ZERO,
+            align: Vec3::ZERO,
+            cohere: Vec3::ZERO,
+            steer_count: 0,
+            align_count: 0,
+            cohere_count: 0,
+        }
+    }
+}
+
+/// Returns a force steering a boid moving with `boid_vel` towards `direction` at full speed, limited by `max_force`.
+/// Returns zero if `direction` is zero.
+#[inline(always)]
+pub fn steer_towards(direction: Vec3, boid_vel: Vec3, max_speed: f32, max_force: f32) -> Vec3 {
+    if direction.length_squared() > 0.0 {
+        (direction.normalize() * max_speed - boid_vel).clamp_length_max(max_force)
+    } else {
+        Vec3::ZERO
+    }
+}
+
+/// Calculates the force to apply to a boid from its neighbours (queried through `other_boids`),
+/// its flock (eg. the flock targets) and the environment (eg. obstacles).
+pub fn calculate_boid<P: Partition + ?Sized>(
+    boid: &Boid,
+    flock: &Flock,
+    other_boids: &P,
+    environment: &Environment,
+) -> Vec3 {
+    let Boid {
+        position: boid_pos,
+        velocity: boid_vel,
+        properties,
+        external_repulsion,
+        wander,
+    } = *boid;
+    let boid_props = flock.boid_properties[properties];
+    let flock_props = flock.properties;
+
+    let heading = boid_vel.normalize_or_zero();
+    let fov_seperation = FlockProperties::fov_cos(flock_props.fov_seperation);
+    let fov_alignment = FlockProperties::fov_cos(flock_props.fov_alignment);
+    let fov_cohesion = FlockProperties::fov_cos(flock_props.fov_cohesion);
+
+    let mut calced = CalcArgs::identity();
+    let mut visit = |aboid_pos: Vec3, aboid_vel: Vec3| {
+        let dist = boid_pos.distance_squared(aboid_pos);
+        if dist > f32::EPSILON {
+            // a boid that isn't moving has no heading, so it sees all around itself
+            let view = if heading == Vec3::ZERO {
+                1.0
+            } else {
+                heading.dot(aboid_pos - boid_pos) / f32::sqrt(dist)
+            };
+            if dist < flock_props.goal_seperation && view >= fov_seperation {
+                let diff = (boid_pos.sub(aboid_pos)).normalize() / f32::sqrt(dist);
+                calced.steer += diff;
+                calced.steer_count += 1;
+            }
+            if dist < flock_props.goal_alignment && view >= fov_alignment {
+                calced.align += aboid_vel;
+                calced.align_count += 1;
+            }
+            if dist < flock_props.goal_cohesion && view >= fov_cohesion {
+                calced.cohere += aboid_pos;
+                calced.cohere_count += 1;
+            }
+        }
+    };
+    let radius = flock_props.max_radius();
+    match flock.bounds {
+        // neighbours across the edges of wrapping bounds are moved next to this boid
+        Some(bounds) => bounds.for_each_image(boid_pos, radius, |shift| {
+            other_boids.for_each_near(boid_pos + shift, radius, |pos, vel| visit(pos - shift, vel))
+        }),
+        None => other_boids.for_each_near(boid_pos, radius, visit),
+    }
+
+    if calced.steer_count > 0 {
+        calced.steer /= calced.steer_count as f32;
+    }
+    if calced.align_count > 0 {
+        calced.align /= calced.align_count as f32;
+    }
+    if calced.cohere_count > 0 {
+        calced.cohere /= calced.cohere_count as f32;
+        calced.cohere -= boid_pos;
+    }
+
+    let max_speed = boid_props.max_speed;
+    let max_force = boid_props.max_force;
+    calced.align = steer_towards(calced.align, boid_vel, max_speed, max_force);
+    calced.steer = steer_towards(calced.steer, boid_vel, max_speed, max_force);
+    calced.cohere = steer_towards(calced.cohere, boid_vel, max_speed, max_force);
+
+    let target: Vec3 = flock
+        .targets
+        .iter()
+        .map(|target| target.force(boid_pos, boid_vel, &boid_props))
+        .chain(
+            flock
+                .path
+                .as_ref()
+                .map(|path| path.force(boid_pos, boid_vel, &boid_props)),
+        )
+        .sum();
+
+    let (obstacle_repulsion, flow) =
+        environment.forces_in(&flock.environment_space, boid_pos, boid_vel, &boid_props);
+    let bounds_repulsion = flock.bounds.map_or(Vec3::ZERO, |b| b.repulsion(boid_pos));
+    let repulsion = obstacle_repulsion + external_repulsion + bounds_repulsion;
+    let avoid =
+        steer_towards(repulsion, boid_vel, max_speed, max_force) * repulsion.length().min(1.0);
+
+    // the wander circle is one second ahead of the boid
+    let wander = if boid_props.wander_strength != 0.0 {
+        steer_towards(boid_vel + wander, boid_vel, max_speed, max_force)
+    } else {
+        Vec3::ZERO
+    };
+
+    let steer_force = calced.steer * boid_props.seperation;
+    let align_force = calced.align * boid_props.alignment;
+    let cohere_force = calced.cohere * boid_props.cohesion;
+    let target_force = target * boid_props.targeting;
+    let avoid_force = avoid * boid_props.avoidance;
+    let wander_force = wander * boid_props.wander_strength;
+    let flow_force = flow * boid_props.flow;
+    steer_force
+        + align_force
+        + cohere_force
+        + target_force
+        + avoid_force
+        + wander_force
+        + flow_force
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FlockPartition, Partitioning, Rng, Target, TargetMode};
+
+    #[test]
+    fn partitions_match_brute_force() {
+        let boid_props = BoidProperties::default();
+        let flock_props = FlockProperties::default();
+        let mut rng = Rng::new(0x9E37_79B9_7F4A_7C15);
+        for (dims, count, extent) in [(2, 500, 400.0), (3, 500, 150.0), (2, 50, 2000.0)] {
+            let boids: Vec<(Vec3, Vec3)> = (0..count)
+                .map(|_| {
+                    (
+                        rng.next_vec3(dims) * extent,
+                        rng.next_vec3(dims) * boid_props.max_speed,
+                    )
+                })
+                .collect();
+            let mut flock = match dims {
+                2 => Flock::new_2d(flock_props),
+                _ => Flock::new_3d(flock_props),
+            };
+            flock.boid_properties.push(boid_props);
+            flock
+                .targets
+                .push(Target::new(rng.next_vec3(dims) * extent, TargetMode::Seek));
+            let environment = Environment::default();
+            for partitioning in [Partitioning::Grid, Partitioning::Tree] {
+                let partition = FlockPartition::new(
+                    partitioning,
+                    boids.clone(),
+                    flock_props.max_radius(),
+                    dims,
+                );
+                for (pos, vel) in boids.iter().copied() {
+                    let boid = Boid::new(pos, vel, 0);
+                    let brute = calculate_boid(&boid, &flock, boids.as_slice(), &environment);
+                    let partitioned = calculate_boid(&boid, &flock, &partition, &environment);
+                    // partitions visit neighbours in a different order, so the sums round differently
+                    assert!(
+                        brute.abs_diff_eq(partitioned, brute.length().max(1.0) * 1e-4),
+                        "{partitioning:?} force {partitioned} differs from brute force {brute}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn neighbours_behind_are_outside_fov() {
+        let boid = Boid::new(Vec3::ZERO, Vec3::X * 100.0, 0);
+        // a single neighbour right behind the boid, moving the other way
+        let behind = [(Vec3::new(-10.0, 0.0, 0.0), Vec3::NEG_Y * 100.0)];
+        let environment = Environment::default();
+
+        let mut all_around = Flock::new_2d(FlockProperties::default());
+        all_around.boid_properties.push(BoidProperties::default());
+        let force = calculate_boid(&boid, &all_around, behind.as_slice(), &environment);
+        assert!(force.length() > 0.0);
+
+        let mut flock = Flock::new_2d(FlockProperties {
+            fov_seperation: 270.0,
+            fov_alignment: 270.0,
+            fov_cohesion: 270.0,
+            ..Default::default()
+        });
+        flock.boid_properties.push(BoidProperties::default());
+        let force = calculate_boid(&boid, &flock, behind.as_slice(), &environment);
+        assert_eq!(force, Vec3::ZERO);
+
+        // only cohesion can see it now, which pulls the boid backwards
+        flock.properties.fov_cohesion = 360.0;
+        let force = calculate_boid(&boid, &flock, behind.as_slice(), &environment);
+        assert!(force.x < 0.0);
+        assert_eq!(force.y, 0.0);
+    }
+
+    #[test]
+    fn drag_slows_down_to_min_speed() {
+        let props = BoidProperties {
+            min_speed: 50.0,
+            linear_drag: 0.5,
+            quadratic_drag: 0.01,
+            ..Default::default()
+        };
+        let mut boid = Boid::new(Vec3::ZERO, Vec3::X * 200.0, 0);
+        boid.apply_force(&props, Vec3::ZERO, 1.0 / 60.0);
+        let speed = boid.velocity.length();
+        assert!(speed < 200.0 && speed > 50.0);
+
+        for _ in 0..600 {
+            boid.apply_force(&props, Vec3::ZERO, 1.0 / 60.0);
+        }
+        assert!((boid.velocity.length() - 50.0).abs() < 1e-3);
+        assert!(boid.velocity.normalize().abs_diff_eq(Vec3::X, 1e-5));
+    }
+}
@@ -0,0 +1,180 @@
+use glam::*;
+
+use crate::{Obstacle, ObstacleShape};
+
+/// How boids are kept inside of `Bounds`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BoundsMode {
+    /// Boids leaving the bounds come back in on the opposite side.
+    /// Neighbours are found across the edges too, as if the bounds were a torus.
+    #[default]
+    Wrap,
+    /// Boids bounce off the edges of the bounds.
+    Bounce,
+    /// Boids steer back inside, more strongly the closer they get to the edges.
+    SteerBack,
+}
+
+/// An axis aligned box (in the space of the flock) that boids are kept inside of.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bounds {
+    pub center: Vec3,
+    /// Half of the size of the bounds. Axes that are infinite (eg. Z for 2D flocks) are unbounded.
+    pub half_extents: Vec3,
+    pub mode: BoundsMode,
+    /// Distance from the edges at which boids start steering back, for `BoundsMode::SteerBack`.
+    pub margin: f32,
+}
+
+impl Bounds {
+    /// Creates bounds for a 2D flock, from `min` to `max`.
+    pub fn new_2d(min: Vec2, max: Vec2, mode: BoundsMode) -> Self {
+        Self {
+            center: ((min + max) * 0.5).extend(0.0),
+            half_extents: ((max - min) * 0.5).abs().extend(f32::INFINITY),
+            mode,
+            margin: 0.0,
+        }
+    }
+
+    /// Creates bounds for a 3D flock, from `min` to `max`.
+    pub fn new_3d(min: Vec3, max: Vec3, mode: BoundsMode) -> Self {
+        Self {
+            center: (min + max) * 0.5,
+            half_extents: ((max - min) * 0.5).abs(),
+            mode,
+            margin: 0.0,
+        }
+    }
+
+    pub fn with_margin(mut self, margin: f32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Returns a vector pointing back inside the bounds, for `BoundsMode::SteerBack`.
+    /// Works like the repulsion of an inverted box obstacle, see `Obstacle::repulsion`.
+    #[inline(always)]
+    pub fn repulsion(&self, pos: Vec3) -> Vec3 {
+        if self.mode != BoundsMode::SteerBack {
+            return Vec3::ZERO;
+        }
+        Obstacle::new(
+            self.center,
+            ObstacleShape::Box {
+                half_extents: self.half_extents,
+            },
+            self.margin,
+        )
+        .inverted(true)
+        .repulsion(pos)
+    }
+
+    /// Moves a boid that left the bounds back in, by wrapping it around or bouncing it off the edges.
+    #[inline(always)]
+    pub fn contain(&self, position: &mut Vec3, velocity: &mut Vec3) {
+        let min = self.center - self.half_extents;
+        let max = self.center + self.half_extents;
+        for axis in 0..3 {
+            if !self.half_extents[axis].is_finite() {
+                continue;
+            }
+            match self.mode {
+                BoundsMode::Wrap => {
+                    let size = self.half_extents[axis] * 2.0;
+                    if size > 0.0 {
+                        position[axis] = min[axis] + (position[axis] - min[axis]).rem_euclid(size);
+                    }
+                }
+                BoundsMode::Bounce => {
+                    if position[axis] < min[axis] {
+                        position[axis] = (2.0 * min[axis] - position[axis]).min(max[axis]);
+                        velocity[axis] = velocity[axis].abs();
+                    } else if position[axis] > max[axis] {
+                        position[axis] = (2.0 * max[axis] - position[axis]).max(min[axis]);
+                        velocity[axis] = -velocity[axis].abs();
+                    }
+                }
+                BoundsMode::SteerBack => {}
+            }
+        }
+    }
+
+    /// Calls `f` with every offset that neighbours within `radius` of `pos` can be found at.
+    /// That's only zero, unless the bounds wrap and `pos` is close to an edge,
+    /// in which case neighbours on the other side of the edges are found at offsets of the size of the bounds.
+    #[inline(always)]
+    pub fn for_each_image(&self, pos: Vec3, radius: f32, mut f: impl FnMut(Vec3)) {
+        if self.mode != BoundsMode::Wrap {
+            f(Vec3::ZERO);
+            return;
+        }
+        let offset = pos - self.center;
+        // every axis has no shift, and maybe one towards the opposite edge
+        let mut shifts = [[0.0; 2]; 3];
+        let mut counts = [1; 3];
+        for axis in 0..3 {
+            let half = self.half_extents[axis];
+            if !half.is_finite() {
+                continue;
+            }
+            if offset[axis] > half - radius {
+                shifts[axis][1] = -half * 2.0;
+                counts[axis] = 2;
+            } else if offset[axis] < radius - half {
+                shifts[axis][1] = half * 2.0;
+                counts[axis] = 2;
+            }
+        }
+        for &x in &shifts[0][..counts[0]] {
+            for &y in &shifts[1][..counts[1]] {
+                for &z in &shifts[2][..counts[2]] {
+                    f(vec3(x, y, z));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_and_bounce_keep_boids_inside() {
+        let wrap = Bounds::new_2d(Vec2::ZERO, vec2(100.0, 50.0), BoundsMode::Wrap);
+        let (mut pos, mut vel) = (vec3(105.0, -5.0, 0.0), vec3(10.0, -10.0, 0.0));
+        wrap.contain(&mut pos, &mut vel);
+        assert!(pos.abs_diff_eq(vec3(5.0, 45.0, 0.0), 1e-4));
+        assert_eq!(vel, vec3(10.0, -10.0, 0.0));
+
+        let bounce = Bounds {
+            mode: BoundsMode::Bounce,
+            ..wrap
+        };
+        let (mut pos, mut vel) = (vec3(105.0, -5.0, 0.0), vec3(10.0, -10.0, 0.0));
+        bounce.contain(&mut pos, &mut vel);
+        assert!(pos.abs_diff_eq(vec3(95.0, 5.0, 0.0), 1e-4));
+        assert_eq!(vel, vec3(-10.0, 10.0, 0.0));
+    }
+
+    #[test]
+    fn wrapped_neighbours_are_found_across_edges() {
+        let bounds = Bounds::new_2d(Vec2::ZERO, vec2(100.0, 100.0), BoundsMode::Wrap);
+        let mut shifts = Vec::new();
+        bounds.for_each_image(vec3(95.0, 2.0, 0.0), 10.0, |shift| shifts.push(shift));
+        assert_eq!(
+            shifts,
+            [
+                Vec3::ZERO,
+                vec3(0.0, 100.0, 0.0),
+                vec3(-100.0, 0.0, 0.0),
+                vec3(-100.0, 100.0, 0.0),
+            ]
+        );
+
+        let mut shifts = Vec::new();
+        bounds.for_each_image(vec3(50.0, 50.0, 0.0), 10.0, |shift| shifts.push(shift));
+        assert_eq!(shifts, [Vec3::ZERO]);
+    }
+}
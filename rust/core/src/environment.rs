@@ -0,0 +1,137 @@
+use glam::*;
+
+use crate::{BoidProperties, FlowField, Obstacle};
+
+/// Things in the world that boids react to, but that aren't boids themselves.
+#[derive(Clone, Debug, Default)]
+pub struct Environment {
+    pub obstacles: Vec<Obstacle>,
+    pub flow_fields: Vec<FlowField>,
+}
+
+/// Converts between the space of a flock, which its boids live in, and the space of the environment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnvironmentSpace {
+    to_environment: Affine3A,
+    from_environment: Mat3A,
+}
+
+impl Default for EnvironmentSpace {
+    fn default() -> Self {
+        Self::new(Affine3A::IDENTITY)
+    }
+}
+
+impl EnvironmentSpace {
+    /// Creates the space of a flock whose positions `to_environment` transforms into the environment
+    /// (eg. the global transform of the flock node).
+    pub fn new(to_environment: Affine3A) -> Self {
+        Self {
+            to_environment,
+            from_environment: to_environment.matrix3.inverse(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn to_environment_point(&self, pos: Vec3) -> Vec3 {
+        self.to_environment.transform_point3(pos)
+    }
+
+    /// Rotates a vector into the environment, keeping its length so forces don't change with the scale of the flock.
+    #[inline(always)]
+    pub fn to_environment_vector(&self, vec: Vec3) -> Vec3 {
+        self.to_environment
+            .transform_vector3(vec)
+            .normalize_or_zero()
+            * vec.length()
+    }
+
+    /// Rotates a vector from the environment back into the flock, keeping its length.
+    #[inline(always)]
+    pub fn from_environment_vector(&self, vec: Vec3) -> Vec3 {
+        self.from_environment.mul_vec3(vec).normalize_or_zero() * vec.length()
+    }
+}
+
+impl Environment {
+    /// Sums up how much every obstacle pushes a boid at `pos` away.
+    #[inline(always)]
+    pub fn obstacle_repulsion(&self, pos: Vec3) -> Vec3 {
+        self.obstacles
+            .iter()
+            .map(|obstacle| obstacle.repulsion(pos))
+            .sum()
+    }
+
+    /// Sums up how much every flow field pushes a boid at `pos` along.
+    #[inline(always)]
+    pub fn flow(&self, pos: Vec3, vel: Vec3, props: &BoidProperties) -> Vec3 {
+        self.flow_fields
+            .iter()
+            .map(|field| field.force(pos, vel, props))
+            .sum()
+    }
+
+    /// Calculates how the environment pushes a boid of a flock in `space`, returning the obstacle repulsion and the flow force
+    /// in the space of the flock.
+    #[inline(always)]
+    pub fn forces_in(
+        &self,
+        space: &EnvironmentSpace,
+        pos: Vec3,
+        vel: Vec3,
+        props: &BoidProperties,
+    ) -> (Vec3, Vec3) {
+        if self.obstacles.is_empty() && self.flow_fields.is_empty() {
+            return (Vec3::ZERO, Vec3::ZERO);
+        }
+        let pos = space.to_environment_point(pos);
+        let vel = space.to_environment_vector(vel);
+        let repulsion = space.from_environment_vector(self.obstacle_repulsion(pos));
+        let flow = space.from_environment_vector(self.flow(pos, vel, props));
+        (repulsion, flow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    use crate::ObstacleShape;
+
+    #[test]
+    fn forces_are_converted_into_flock_space() {
+        let props = BoidProperties::default();
+        // a wall at x = 100 and a field flowing along +X, both in the environment
+        let environment = Environment {
+            obstacles: vec![Obstacle::new(
+                vec3(100.0, 0.0, 0.0),
+                ObstacleShape::Sphere { radius: 10.0 },
+                20.0,
+            )],
+            flow_fields: vec![FlowField::new(
+                UVec3::ONE,
+                vec![Vec3::X],
+                Affine3A::IDENTITY,
+            )],
+        };
+        // a flock moved to x = 100 and turned a quarter clockwise, so its +Y is the +X of the environment
+        let to_environment = Affine3A::from_translation(vec3(100.0, 0.0, 0.0))
+            * Affine3A::from_rotation_z(-FRAC_PI_2);
+        let space = EnvironmentSpace::new(to_environment);
+
+        let (repulsion, flow) =
+            environment.forces_in(&space, vec3(0.0, -15.0, 0.0), Vec3::ZERO, &props);
+        assert!(repulsion.y < 0.0 && repulsion.x.abs() < 1e-3);
+        assert!(flow.y > 0.0 && flow.x.abs() < 1e-3);
+        // without a transform the flock would be far from the obstacle
+        let (repulsion, _) = environment.forces_in(
+            &EnvironmentSpace::default(),
+            vec3(0.0, -15.0, 0.0),
+            Vec3::ZERO,
+            &props,
+        );
+        assert_eq!(repulsion, Vec3::ZERO);
+    }
+}
@@ -0,0 +1,161 @@
+use glam::*;
+use rayon::prelude::*;
+
+use crate::{
+    calculate_boid, integrate, BoidProperties, BoidStorage, Bounds, Environment, EnvironmentSpace,
+    FlockPartition, FlockProperties, Leader, Partitioning, Path, Rng, Target,
+};
+
+/// A flock of boids.
+/// Boids only flock with other boids in the same flock.
+#[derive(Clone, Debug)]
+pub struct Flock {
+    pub properties: FlockProperties,
+    pub partitioning: Partitioning,
+    /// Whether this flock lives in 2 or 3 dimensions.
+    /// For 2D flocks the Z axis of all vectors is expected to be zero.
+    pub dimensions: usize,
+    /// Positions the flock follows (or flees from).
+    pub targets: Vec<Target>,
+    /// The boid (or position) the boids of this flock follow behind, if any.
+    pub leader: Option<Leader>,
+    /// The path this flock follows along, if any.
+    pub path: Option<Path>,
+    /// Properties shared by the boids of this flock, which refer to them by index.
+    pub boid_properties: Vec<BoidProperties>,
+    /// The box boids are kept inside of, if any.
+    pub bounds: Option<Bounds>,
+    /// How positions of this flock relate to the obstacles and flow fields of the environment.
+    pub environment_space: EnvironmentSpace,
+    /// Random number generator for behaviours like wandering.
+    pub rng: Rng,
+    pub boids: BoidStorage,
+}
+
+impl Flock {
+    /// Creates an empty 2D flock.
+    pub fn new_2d(properties: FlockProperties) -> Self {
+        Self::new(properties, 2)
+    }
+
+    /// Creates an empty 3D flock.
+    pub fn new_3d(properties: FlockProperties) -> Self {
+        Self::new(properties, 3)
+    }
+
+    fn new(properties: FlockProperties, dimensions: usize) -> Self {
+        Self {
+            properties,
+            partitioning: Partitioning::default(),
+            dimensions,
+            targets: Vec::new(),
+            leader: None,
+            path: None,
+            boid_properties: Vec::new(),
+            bounds: None,
+            environment_space: EnvironmentSpace::default(),
+            rng: Rng::default(),
+            boids: BoidStorage::default(),
+        }
+    }
+
+    /// Partitions the boids of this flock with the flock's `partitioning` method.
+    pub fn partition(&self) -> FlockPartition {
+        self.partition_with_cell_size(self.properties.max_radius())
+    }
+
+    /// Partitions the boids of this flock with the flock's `partitioning` method,
+    /// sizing grid cells for queries with a radius of `cell_size` instead of the flock's own radius.
+    pub fn partition_with_cell_size(&self, cell_size: f32) -> FlockPartition {
+        let posvel = self
+            .boids
+            .positions
+            .iter()
+            .copied()
+            .zip(self.boids.velocities.iter().copied())
+            .collect();
+        FlockPartition::new(self.partitioning, posvel, cell_size, self.dimensions)
+    }
+
+    /// Calculates the forces to apply to every boid in this flock, in the same order as `boids`.
+    pub fn forces(&self, partition: &FlockPartition, environment: &Environment) -> Vec<Vec3> {
+        let leader = self.leader.and_then(|leader| match leader.boid {
+            Some(index) if index >= self.boids.len() => None,
+            Some(index) => Some(Leader {
+                position: self.boids.positions[index],
+                heading: self.boids.velocities[index].normalize_or_zero(),
+                ..leader
+            }),
+            None => Some(leader),
+        });
+        (0..self.boids.len())
+            .into_par_iter()
+            .map(|index| {
+                let boid = self.boids.get(index);
+                let force = calculate_boid(&boid, self, partition, environment);
+                match leader {
+                    Some(leader) if leader.boid != Some(index) => {
+                        let props = &self.boid_properties[boid.properties];
+                        force + leader.force(boid.position, boid.velocity, props) * props.targeting
+                    }
+                    _ => force,
+                }
+            })
+            .collect()
+    }
+
+    /// Moves the wander point of every wandering boid randomly, by up to its `wander_jitter` per second.
+    pub fn wander(&mut self, delta: f32) {
+        let BoidStorage {
+            properties, wander, ..
+        } = &mut self.boids;
+        for (wander, properties) in wander.iter_mut().zip(properties.iter()) {
+            let props = &self.boid_properties[*properties];
+            if props.wander_strength == 0.0 {
+                continue;
+            }
+            let jitter = self.rng.next_vec3(self.dimensions) * props.wander_jitter * delta;
+            let point = match (*wander + jitter).try_normalize() {
+                Some(point) => point,
+                None => self.rng.next_vec3(self.dimensions).normalize_or(Vec3::X),
+            };
+            *wander = point * props.wander_radius;
+        }
+    }
+
+    /// Advances the simulation of this flock by `delta` seconds.
+    pub fn step(&mut self, environment: &Environment, delta: f32) {
+        self.wander(delta);
+        let forces = self.forces(&self.partition(), environment);
+        self.apply_forces(&forces, delta);
+    }
+
+    /// Applies forces calculated by `forces` to the boids of this flock for `delta` seconds.
+    /// Does nothing if no time passed (eg. while the game is paused with a time scale of zero).
+    pub fn apply_forces(&mut self, forces: &[Vec3], delta: f32) {
+        if delta <= 0.0 {
+            return;
+        }
+        let BoidStorage {
+            positions,
+            velocities,
+            properties,
+            accelerations,
+            ..
+        } = &mut self.boids;
+        let boids = positions
+            .iter_mut()
+            .zip(velocities)
+            .zip(properties.iter())
+            .zip(accelerations);
+        for ((((position, velocity), properties), acceleration), force) in boids.zip(forces) {
+            let properties = &self.boid_properties[*properties];
+            let old_velocity = *velocity;
+            integrate(position, velocity, properties, *force, delta);
+            *acceleration = (*velocity - old_velocity) / delta;
+            if let Some(bounds) = &self.bounds {
+                bounds.contain(position, velocity);
+            }
+        }
+    }
+}
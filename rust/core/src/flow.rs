@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use glam::*;
+
+use crate::{steer_towards, BoidProperties};
+
+/// A grid of vectors that boids inside of it align with (eg. wind, a river current or routes through a crowd).
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowField {
+    /// Transforms positions into the space of the grid, where every cell is one unit wide
+    /// and the grid goes from zero to `size`.
+    pub to_grid: Affine3A,
+    /// How many cells the grid has along each axis.
+    /// Axes with a single cell are unbounded (eg. Z for 2D fields).
+    pub size: UVec3,
+    /// The vector of every cell, row by row (X first, then Y, then Z).
+    /// Its length is how strongly boids align with it, up to 1.
+    /// Shared, so the field can be cloned every tick without copying the grid.
+    pub vectors: Arc<[Vec3]>,
+    /// How strong this field is compared to other fields.
+    pub weight: f32,
+}
+
+impl FlowField {
+    pub fn new(size: UVec3, vectors: impl Into<Arc<[Vec3]>>, to_grid: Affine3A) -> Self {
+        Self {
+            to_grid,
+            size,
+            vectors: vectors.into(),
+            weight: 1.0,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    #[inline(always)]
+    fn vector(&self, cell: IVec3) -> Vec3 {
+        let cell = cell.as_uvec3();
+        let index = cell.x + self.size.x * (cell.y + self.size.y * cell.z);
+        self.vectors[index as usize]
+    }
+
+    /// Samples the field at `pos`, blending between the vectors at the centers of the closest cells.
+    /// Returns `None` outside of the field.
+    #[inline(always)]
+    pub fn sample(&self, pos: Vec3) -> Option<Vec3> {
+        if self.vectors.len() != self.size.element_product() as usize || self.vectors.is_empty() {
+            return None;
+        }
+        let grid_pos = self.to_grid.transform_point3(pos);
+        let size = self.size.as_vec3();
+        let bounded = self.size.cmpgt(UVec3::ONE);
+        let inside = grid_pos.cmpge(Vec3::ZERO) & grid_pos.cmplt(size);
+        if (bounded & !inside).any() {
+            return None;
+        }
+
+        let centered = grid_pos - 0.5;
+        let floor = centered.floor();
+        let max = self.size.as_ivec3() - 1;
+        let low = floor.as_ivec3().clamp(IVec3::ZERO, max);
+        let high = (floor.as_ivec3() + 1).clamp(IVec3::ZERO, max);
+        let t = centered - floor;
+        let corner = |x: bool, y: bool, z: bool| {
+            let cell = IVec3::select(BVec3::new(x, y, z), high, low);
+            self.vector(cell)
+        };
+        let lerp_x = |y, z| corner(false, y, z).lerp(corner(true, y, z), t.x);
+        let lerp_y = |z| lerp_x(false, z).lerp(lerp_x(true, z), t.y);
+        Some(lerp_y(false).lerp(lerp_y(true), t.z))
+    }
+
+    /// Calculates the (weighted) force aligning a boid at `boid_pos` with this field.
+    #[inline(always)]
+    pub fn force(&self, boid_pos: Vec3, boid_vel: Vec3, boid_props: &BoidProperties) -> Vec3 {
+        let Some(flow) = self.sample(boid_pos) else {
+            return Vec3::ZERO;
+        };
+        let force = steer_towards(flow, boid_vel, boid_props.max_speed, boid_props.max_force);
+        force * flow.length().min(1.0) * self.weight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_blend_between_cells() {
+        // two cells, 10 units wide, starting at x = 100
+        let to_grid = Affine3A::from_scale(Vec3::splat(0.1))
+            * Affine3A::from_translation(vec3(-100.0, 0.0, 0.0));
+        let field = FlowField::new(uvec3(2, 1, 1), vec![Vec3::X, Vec3::Y], to_grid);
+
+        assert_eq!(field.sample(vec3(105.0, 5.0, 0.0)), Some(Vec3::X));
+        assert_eq!(field.sample(vec3(115.0, 5.0, 0.0)), Some(Vec3::Y));
+        let middle = field.sample(vec3(110.0, 5.0, 0.0)).unwrap();
+        assert!(middle.abs_diff_eq(vec3(0.5, 0.5, 0.0), 1e-5));
+        // Y and Z only have one cell, so they're unbounded
+        assert_eq!(field.sample(vec3(105.0, -500.0, 50.0)), Some(Vec3::X));
+        assert_eq!(field.sample(vec3(95.0, 5.0, 0.0)), None);
+
+        let props = BoidProperties::default();
+        let force = field
+            .with_weight(0.5)
+            .force(vec3(105.0, 5.0, 0.0), Vec3::ZERO, &props);
+        assert_eq!(force, Vec3::X * props.max_speed * 0.5);
+    }
+}
@@ -0,0 +1,90 @@
+use glam::*;
+
+use crate::{steer_towards, BoidProperties, Target, TargetMode};
+
+/// A boid (or anything else) the boids of a flock follow in a line behind, instead of flocking towards a target.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Leader {
+    /// Index of the boid in the flock that leads it, which doesn't follow itself.
+    /// Its position and velocity are used instead of `position` and `heading`.
+    pub boid: Option<usize>,
+    pub position: Vec3,
+    /// Direction the leader is going in, normalized.
+    pub heading: Vec3,
+    /// How far behind the leader boids follow it.
+    /// Boids that are ahead of the leader (by less than this) get out of its way.
+    pub distance: f32,
+    /// How far from the path of the leader boids that are ahead of it stay.
+    pub clearance: f32,
+}
+
+impl Leader {
+    /// Creates a leader at `position`, going in the direction of `heading`.
+    pub fn new(position: Vec3, heading: Vec3) -> Self {
+        Self {
+            boid: None,
+            position,
+            heading: heading.normalize_or_zero(),
+            distance: 50.0,
+            clearance: 30.0,
+        }
+    }
+
+    /// Creates a leader that is the boid at `index` in the flock.
+    pub fn boid(index: usize) -> Self {
+        Self {
+            boid: Some(index),
+            ..Self::new(Vec3::ZERO, Vec3::ZERO)
+        }
+    }
+
+    pub fn with_distance(mut self, distance: f32) -> Self {
+        self.distance = distance;
+        self
+    }
+
+    pub fn with_clearance(mut self, clearance: f32) -> Self {
+        self.clearance = clearance;
+        self
+    }
+
+    /// Calculates the force pulling a boid in behind the leader, or out of its way if it's in the path of the leader.
+    #[inline(always)]
+    pub fn force(&self, boid_pos: Vec3, boid_vel: Vec3, boid_props: &BoidProperties) -> Vec3 {
+        let offset = boid_pos - self.position;
+        let ahead = offset.dot(self.heading);
+        let aside = offset - self.heading * ahead;
+        if ahead > 0.0 && ahead < self.distance && aside.length_squared() < self.clearance.powi(2) {
+            // boids right on the path step aside sideways, within the plane for 2D flocks
+            let away = aside
+                .try_normalize()
+                .unwrap_or_else(|| self.heading.cross(Vec3::Z).normalize_or(Vec3::X));
+            return steer_towards(away, boid_vel, boid_props.max_speed, boid_props.max_force);
+        }
+        let behind = self.position - self.heading * self.distance;
+        Target::new(behind, TargetMode::Arrive).force(boid_pos, boid_vel, boid_props)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn followers_line_up_behind_and_clear_the_path() {
+        let props = BoidProperties::default();
+        let leader = Leader::new(Vec3::ZERO, Vec3::X)
+            .with_distance(50.0)
+            .with_clearance(20.0);
+
+        // following from the side, towards the point behind the leader
+        let force = leader.force(vec3(-50.0, 100.0, 0.0), Vec3::ZERO, &props);
+        assert!(force.x.abs() < 1e-3 && force.y < 0.0);
+
+        // in front of the leader, moving out of its way
+        let force = leader.force(vec3(30.0, 5.0, 0.0), Vec3::ZERO, &props);
+        assert!(force.x.abs() < 1e-3 && force.y > 0.0);
+        let force = leader.force(vec3(30.0, 0.0, 0.0), Vec3::ZERO, &props);
+        assert!(force.x.abs() < 1e-3 && force.y != 0.0 && force.z == 0.0);
+    }
+}
@@ -0,0 +1,40 @@
+//! Godot independent simulation core for the boids addon.
+//!
+//! Everything here works on plain [`glam`] vectors, so flocks can be simulated (and tested)
+//! without a running engine. The GDExtension classes are thin adapters over this crate.
+
+mod boid;
+mod bounds;
+mod environment;
+mod flock;
+mod flow;
+mod leader;
+mod obstacle;
+mod orientation;
+mod partition;
+mod path;
+mod properties;
+mod relation;
+mod rng;
+mod storage;
+mod target;
+mod world;
+
+pub use boid::*;
+pub use bounds::*;
+pub use environment::*;
+pub use flock::*;
+pub use flow::*;
+pub use leader::*;
+pub use obstacle::*;
+pub use orientation::*;
+pub use partition::*;
+pub use path::*;
+pub use properties::*;
+pub use relation::*;
+pub use rng::*;
+pub use storage::*;
+pub use target::*;
+pub use world::*;
+
+pub use glam;
@@ -0,0 +1,163 @@
+use glam::*;
+
+/// The shape of an `Obstacle`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ObstacleShape {
+    /// A single point.
+    Point,
+    /// A circle (for 2D) or a sphere (for 3D).
+    Sphere { radius: f32 },
+    /// A rectangle (for 2D) or a box (for 3D), rotated by the obstacle's `rotation`.
+    /// For rectangles the Z half extent should be infinite, so that boids are never "above" or "below" it.
+    Box { half_extents: Vec3 },
+}
+
+/// Something boids steer away from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obstacle {
+    pub position: Vec3,
+    /// Rotation of the obstacle, only matters for boxes.
+    pub rotation: Quat,
+    pub shape: ObstacleShape,
+    /// Distance from the surface of the obstacle at which boids start avoiding it.
+    pub margin: f32,
+    /// If set, boids are kept inside of the obstacle instead of outside of it.
+    pub inverted: bool,
+}
+
+impl Obstacle {
+    pub fn new(position: Vec3, shape: ObstacleShape, margin: f32) -> Self {
+        Self {
+            position,
+            rotation: Quat::IDENTITY,
+            shape,
+            margin,
+            inverted: false,
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn inverted(mut self, inverted: bool) -> Self {
+        self.inverted = inverted;
+        self
+    }
+
+    /// Returns the distance of `pos` to the surface of this obstacle (negative if on the wrong side of it),
+    /// and the direction pointing away from the surface, towards where boids should be.
+    #[inline(always)]
+    pub fn surface(&self, pos: Vec3) -> (f32, Vec3) {
+        let offset = pos - self.position;
+        let (dist, normal) = match self.shape {
+            ObstacleShape::Point => (offset.length(), offset.normalize_or_zero()),
+            ObstacleShape::Sphere { radius } => {
+                (offset.length() - radius, offset.normalize_or_zero())
+            }
+            ObstacleShape::Box { half_extents } => {
+                let (dist, normal) = box_surface(self.rotation.inverse() * offset, half_extents);
+                (dist, self.rotation * normal)
+            }
+        };
+        if self.inverted {
+            (-dist, -normal)
+        } else {
+            (dist, normal)
+        }
+    }
+
+    /// Returns a vector pointing away from this obstacle, that gets longer the closer `pos` is to it.
+    /// It's zero outside of the margin, and has a length of one on (or inside) the surface.
+    #[inline(always)]
+    pub fn repulsion(&self, pos: Vec3) -> Vec3 {
+        let (dist, normal) = self.surface(pos);
+        if dist >= self.margin {
+            return Vec3::ZERO;
+        }
+        let urgency = if self.margin > 0.0 {
+            1.0 - dist.max(0.0) / self.margin
+        } else {
+            1.0
+        };
+        normal * urgency
+    }
+}
+
+/// Signed distance and outwards normal of an axis aligned box centered on the origin.
+/// Negative half extents (of a mirrored box) are treated as positive.
+#[inline(always)]
+fn box_surface(pos: Vec3, half_extents: Vec3) -> (f32, Vec3) {
+    let q = pos.abs() - half_extents.abs();
+    let outside = q.max(Vec3::ZERO);
+    if outside.length_squared() > 0.0 {
+        (outside.length(), (outside * pos.signum()).normalize())
+    } else {
+        // inside, so the closest face is the one we are the least deep into
+        let dist = q.max_element();
+        let axis = if q.x == dist {
+            Vec3::X
+        } else if q.y == dist {
+            Vec3::Y
+        } else {
+            Vec3::Z
+        };
+        (dist, axis * pos.signum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repulsion_grows_towards_surface() {
+        let obstacle = Obstacle::new(Vec3::ZERO, ObstacleShape::Sphere { radius: 10.0 }, 20.0);
+        assert_eq!(obstacle.repulsion(vec3(40.0, 0.0, 0.0)), Vec3::ZERO);
+        assert_eq!(
+            obstacle.repulsion(vec3(20.0, 0.0, 0.0)),
+            vec3(0.5, 0.0, 0.0)
+        );
+        assert_eq!(
+            obstacle.repulsion(vec3(0.0, -5.0, 0.0)),
+            vec3(0.0, -1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn inverted_rect_keeps_boids_inside() {
+        let rect = ObstacleShape::Box {
+            half_extents: vec3(100.0, 50.0, f32::INFINITY),
+        };
+        let obstacle = Obstacle::new(Vec3::ZERO, rect, 10.0).inverted(true);
+        assert_eq!(obstacle.repulsion(Vec3::ZERO), Vec3::ZERO);
+        assert_eq!(
+            obstacle.repulsion(vec3(95.0, 0.0, 0.0)),
+            vec3(-0.5, 0.0, 0.0)
+        );
+        assert_eq!(
+            obstacle.repulsion(vec3(0.0, 60.0, 0.0)),
+            vec3(0.0, -1.0, 0.0)
+        );
+
+        let rotated = obstacle.with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2));
+        let repulsion = rotated.repulsion(vec3(0.0, 95.0, 0.0));
+        assert!(repulsion.abs_diff_eq(vec3(0.0, -0.5, 0.0), 1e-5));
+
+        // a rect mirrored along X (eg. by a negative scale) is the same rect
+        let mirrored = Obstacle::new(
+            Vec3::ZERO,
+            ObstacleShape::Box {
+                half_extents: vec3(-100.0, 50.0, f32::INFINITY),
+            },
+            10.0,
+        )
+        .inverted(true);
+        assert_eq!(mirrored.repulsion(Vec3::ZERO), Vec3::ZERO);
+        assert_eq!(
+            mirrored.repulsion(vec3(-95.0, 0.0, 0.0)),
+            vec3(0.5, 0.0, 0.0)
+        );
+    }
+}
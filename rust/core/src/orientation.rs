@@ -0,0 +1,123 @@
+use std::f32::consts::{PI, TAU};
+
+use glam::*;
+
+/// How a boid turns to face the direction it's moving in.
+#[derive(Clone, Copy, Debug)]
+pub struct Orientation {
+    /// How fast a boid can turn, in radians per second.
+    pub max_turn_rate: f32,
+    /// Which way is up for 3D boids. Boids keep their Y axis as close to this as possible.
+    pub up: Vec3,
+    /// How far 3D boids roll into turns, in radians per unit of sideways acceleration.
+    pub banking: f32,
+    /// The largest roll (in radians) banking can cause.
+    pub max_bank: f32,
+}
+
+impl Default for Orientation {
+    fn default() -> Self {
+        Self {
+            max_turn_rate: f32::INFINITY,
+            up: Vec3::Y,
+            banking: 0.0,
+            max_bank: PI / 4.0,
+        }
+    }
+}
+
+impl Orientation {
+    /// Returns the rotation (in radians) of a 2D boid that faces `velocity` with its +X axis,
+    /// turning from `current` by at most `max_turn_rate * delta`.
+    /// Boids that aren't moving, or don't have any time to turn, keep their rotation.
+    pub fn rotation_2d(&self, current: f32, velocity: Vec3, delta: f32) -> f32 {
+        if delta <= 0.0 || velocity.x == 0.0 && velocity.y == 0.0 {
+            return current;
+        }
+        let target = velocity.y.atan2(velocity.x);
+        // the shortest way around, between -PI and PI
+        let diff = (target - current + PI).rem_euclid(TAU) - PI;
+        let max_turn = self.max_turn_rate * delta;
+        current + diff.clamp(-max_turn, max_turn)
+    }
+
+    /// Returns the rotation of a 3D boid that faces `velocity` with its -Z axis (like `Node3D.look_at`),
+    /// turning from `current` by at most `max_turn_rate * delta`.
+    /// The boid rolls into turns by its sideways `acceleration`, see `banking`.
+    /// Boids that aren't moving, move straight along `up` or don't have any time to turn, keep their rotation.
+    pub fn rotation_3d(
+        &self,
+        current: Quat,
+        velocity: Vec3,
+        acceleration: Vec3,
+        delta: f32,
+    ) -> Quat {
+        if delta <= 0.0 {
+            return current;
+        }
+        let forward = velocity.normalize_or_zero();
+        let right = forward.cross(self.up).normalize_or_zero();
+        if right == Vec3::ZERO {
+            return current;
+        }
+        let up = right.cross(forward);
+        let facing = Quat::from_mat3(&Mat3::from_cols(right, up, -forward));
+        // rolling around the forward axis lowers the right side, towards where the boid is turning
+        let bank = (acceleration.dot(right) * self.banking).clamp(-self.max_bank, self.max_bank);
+        let target = Quat::from_axis_angle(forward, bank) * facing;
+        current
+            .normalize()
+            .rotate_towards(target, self.max_turn_rate * delta)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn turn_rate_limits_rotation() {
+        let orientation = Orientation {
+            max_turn_rate: PI / 2.0,
+            ..Default::default()
+        };
+        // facing +X, turning to face -Y by the shortest way
+        let rotation = orientation.rotation_2d(0.0, vec3(0.0, -1.0, 0.0), 0.5);
+        assert!((rotation + PI / 4.0).abs() < 1e-5);
+
+        let unlimited = Orientation::default();
+        let rotation = unlimited.rotation_3d(Quat::IDENTITY, Vec3::X, Vec3::ZERO, 0.1);
+        assert!(rotation.mul_vec3(Vec3::NEG_Z).abs_diff_eq(Vec3::X, 1e-5));
+        assert!(rotation.mul_vec3(Vec3::Y).abs_diff_eq(Vec3::Y, 1e-5));
+    }
+
+    #[test]
+    fn banks_into_turns() {
+        let orientation = Orientation {
+            banking: 0.1,
+            ..Default::default()
+        };
+        // moving along -Z, accelerating to the right (+X)
+        let rotation = orientation.rotation_3d(Quat::IDENTITY, Vec3::NEG_Z, Vec3::X, 1.0);
+        let right = rotation.mul_vec3(Vec3::X);
+        assert!(right.y < 0.0);
+        assert!((right.y + 0.1f32.sin()).abs() < 1e-5);
+
+        // banking is limited by `max_bank`
+        let rotation = orientation.rotation_3d(Quat::IDENTITY, Vec3::NEG_Z, Vec3::X * 100.0, 1.0);
+        let right = rotation.mul_vec3(Vec3::X);
+        assert!((right.y + (PI / 4.0).sin()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn zero_delta_keeps_rotation() {
+        // an infinite turn rate times a zero delta would be NaN
+        let orientation = Orientation::default();
+        assert_eq!(orientation.rotation_2d(1.0, Vec3::NEG_Y, 0.0), 1.0);
+        let rotation = Quat::from_rotation_y(1.0);
+        assert_eq!(
+            orientation.rotation_3d(rotation, Vec3::X, Vec3::ZERO, 0.0),
+            rotation
+        );
+    }
+}
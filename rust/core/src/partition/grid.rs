@@ -0,0 +1,116 @@
+use glam::*;
+use rustc_hash::FxHashMap;
+
+use super::Partition;
+
+/// Cells smaller than this are too fine to be useful, grids built with them put every boid into a single cell instead.
+const MIN_CELL_SIZE: f32 = 1e-3;
+
+/// A uniform spatial hash grid.
+/// Boids are sorted by the cell they are in, so every cell is a contiguous range of boids.
+pub struct SpatialGrid {
+    inv_cell_size: f32,
+    /// Whether the grid is 2D (only one layer of cells along the Z axis) or 3D.
+    dims: usize,
+    cells: FxHashMap<IVec3, (u32, u32)>,
+    boids: Vec<(Vec3, Vec3)>,
+}
+
+impl SpatialGrid {
+    /// Builds a grid out of `boids`, where each cell is `cell_size` wide.
+    /// For the best results `cell_size` should be the largest radius the grid will be queried with.
+    /// For 2D grids (`dims` of 2) the Z axis of all positions is expected to be zero.
+    pub fn new(boids: &[(Vec3, Vec3)], cell_size: f32, dims: usize) -> Self {
+        // a scale of zero maps every position to the same cell
+        let inv_cell_size = if cell_size >= MIN_CELL_SIZE {
+            1.0 / cell_size
+        } else {
+            0.0
+        };
+
+        let mut keyed: Vec<(IVec3, (Vec3, Vec3))> = boids
+            .iter()
+            .map(|boid| (cell_of(boid.0, inv_cell_size), *boid))
+            .collect();
+        keyed.sort_unstable_by_key(|(cell, _)| cell.to_array());
+
+        let mut cells = FxHashMap::default();
+        let mut start = 0;
+        for (idx, (cell, _)) in keyed.iter().enumerate() {
+            let next = keyed.get(idx + 1).map(|(cell, _)| cell);
+            if next != Some(cell) {
+                cells.insert(*cell, (start, idx as u32 + 1));
+                start = idx as u32 + 1;
+            }
+        }
+
+        Self {
+            inv_cell_size,
+            dims,
+            cells,
+            boids: keyed.into_iter().map(|(_, boid)| boid).collect(),
+        }
+    }
+}
+
+impl Partition for SpatialGrid {
+    #[inline(always)]
+    fn for_each_near(&self, pos: Vec3, radius: f32, mut f: impl FnMut(Vec3, Vec3)) {
+        let mut min = cell_of(pos - radius, self.inv_cell_size);
+        let mut max = cell_of(pos + radius, self.inv_cell_size);
+        if self.dims == 2 {
+            // every boid is in the same layer, so there's no need to look above or below it
+            (min.z, max.z) = (0, 0);
+        }
+        // walking more cells than there are occupied ones is slower than just visiting every boid
+        let range = max.as_i64vec3() - min.as_i64vec3() + 1;
+        let cell_count = range.x.saturating_mul(range.y).saturating_mul(range.z);
+        if cell_count > self.cells.len() as i64 {
+            for (pos, vel) in &self.boids {
+                f(*pos, *vel);
+            }
+            return;
+        }
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let Some((start, end)) = self.cells.get(&ivec3(x, y, z)) else {
+                        continue;
+                    };
+                    for (pos, vel) in &self.boids[*start as usize..*end as usize] {
+                        f(*pos, *vel);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[inline(always)]
+fn cell_of(pos: Vec3, inv_cell_size: f32) -> IVec3 {
+    (pos * inv_cell_size).floor().as_ivec3()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn degenerate_queries_visit_every_boid() {
+        let boids: Vec<(Vec3, Vec3)> = (0..100)
+            .map(|i| (vec3(i as f32 * 3.0, i as f32 * -5.0, 0.0), Vec3::ZERO))
+            .collect();
+        let count = |grid: &SpatialGrid, radius| {
+            let mut count = 0;
+            grid.for_each_near(Vec3::ZERO, radius, |_, _| count += 1);
+            count
+        };
+        // zero sized cells (a flock with all goals at zero) fall back to a single cell
+        let grid = SpatialGrid::new(&boids, 0.0, 2);
+        assert_eq!(count(&grid, 1.0), boids.len());
+        // a radius spanning far more cells than there are boids
+        let grid = SpatialGrid::new(&boids, 1.0, 3);
+        assert_eq!(count(&grid, 1e6), boids.len());
+        assert_eq!(count(&grid, f32::INFINITY), boids.len());
+    }
+}
@@ -0,0 +1,73 @@
+use glam::*;
+
+pub mod grid;
+pub mod tree;
+
+pub use grid::*;
+pub use tree::*;
+
+/// A spatial structure that can be queried for boids near a position.
+pub trait Partition: Send + Sync {
+    /// Calls `f` with the position and velocity of every boid that may be within `radius` of `pos`.
+    /// Boids outside of `radius` may be visited as well, callers are expected to do their own distance checks.
+    fn for_each_near(&self, pos: Vec3, radius: f32, f: impl FnMut(Vec3, Vec3));
+}
+
+/// Brute force "partitioning", visits every boid.
+impl Partition for [(Vec3, Vec3)] {
+    #[inline(always)]
+    fn for_each_near(&self, _: Vec3, _: f32, mut f: impl FnMut(Vec3, Vec3)) {
+        for (pos, vel) in self {
+            f(*pos, *vel);
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+/// How a flock partitions its boids to speed up neighbour lookups.
+pub enum Partitioning {
+    /// Check every boid against every other boid in the flock.
+    BruteForce,
+    #[default]
+    /// Use a uniform grid, with cells sized from the largest radius in the flock properties.
+    /// Works best when boids are spread evenly.
+    Grid,
+    /// Use a quadtree for 2D flocks, or an octree for 3D flocks.
+    /// Works best when the density of boids is uneven.
+    Tree,
+}
+
+/// A flock's boids, partitioned with one of the `Partitioning` methods.
+pub enum FlockPartition {
+    BruteForce(Vec<(Vec3, Vec3)>),
+    Grid(SpatialGrid),
+    Tree(Tree),
+}
+
+impl FlockPartition {
+    /// Partitions `boids`. `cell_size` is used by the grid, and `dims` (2 or 3) selects between a 2D and a 3D grid, or a quadtree and an octree.
+    pub fn new(
+        partitioning: Partitioning,
+        boids: Vec<(Vec3, Vec3)>,
+        cell_size: f32,
+        dims: usize,
+    ) -> Self {
+        match partitioning {
+            Partitioning::BruteForce => Self::BruteForce(boids),
+            Partitioning::Grid => Self::Grid(SpatialGrid::new(&boids, cell_size, dims)),
+            Partitioning::Tree if dims == 2 => Self::Tree(Tree::quadtree(&boids)),
+            Partitioning::Tree => Self::Tree(Tree::octree(&boids)),
+        }
+    }
+}
+
+impl Partition for FlockPartition {
+    #[inline(always)]
+    fn for_each_near(&self, pos: Vec3, radius: f32, f: impl FnMut(Vec3, Vec3)) {
+        match self {
+            Self::BruteForce(boids) => boids.for_each_near(pos, radius, f),
+            Self::Grid(grid) => grid.for_each_near(pos, radius, f),
+            Self::Tree(tree) => tree.for_each_near(pos, radius, f),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use glam::*;
+
+use super::Partition;
+
+/// How many boids a node can hold before it gets split.
+const LEAF_CAPACITY: usize = 16;
+/// How deep the tree can get, so that many boids on the exact same position can't split forever.
+const MAX_DEPTH: u32 = 16;
+/// How many nodes a query can have waiting to be visited: up to 7 siblings for every level it went down, plus the last child.
+const STACK_SIZE: usize = MAX_DEPTH as usize * 7 + 1;
+
+struct Node {
+    min: Vec3,
+    max: Vec3,
+    /// Index of the first child, children are stored next to each other.
+    /// `None` if this node is a leaf.
+    first_child: Option<u32>,
+    start: u32,
+    end: u32,
+}
+
+/// A quadtree (2D) or octree (3D) that partitions boids by position.
+/// Nodes are split until they hold at most a handful of boids, so dense areas get small nodes
+/// while empty space is covered by few (or no) nodes.
+pub struct Tree {
+    dims: usize,
+    nodes: Vec<Node>,
+    boids: Vec<(Vec3, Vec3)>,
+}
+
+impl Tree {
+    /// Builds a quadtree out of `boids`, only splitting on the X and Y axes.
+    pub fn quadtree(boids: &[(Vec3, Vec3)]) -> Self {
+        Self::new(boids, 2)
+    }
+
+    /// Builds an octree out of `boids`.
+    pub fn octree(boids: &[(Vec3, Vec3)]) -> Self {
+        Self::new(boids, 3)
+    }
+
+    fn new(boids: &[(Vec3, Vec3)], dims: usize) -> Self {
+        let (min, max) = boids.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), (pos, _)| (min.min(*pos), max.max(*pos)),
+        );
+        let mut tree = Self {
+            dims,
+            nodes: Vec::with_capacity(boids.len() / LEAF_CAPACITY * 2 + 1),
+            boids: boids.to_vec(),
+        };
+        tree.nodes.push(Node {
+            min,
+            max,
+            first_child: None,
+            start: 0,
+            end: boids.len() as u32,
+        });
+        tree.split(0, 0);
+        tree
+    }
+
+    fn split(&mut self, node_idx: usize, depth: u32) {
+        let Node {
+            min,
+            max,
+            start,
+            end,
+            ..
+        } = self.nodes[node_idx];
+        if (end - start) as usize <= LEAF_CAPACITY || depth >= MAX_DEPTH {
+            return;
+        }
+
+        let dims = self.dims;
+        let center = (min + max) * 0.5;
+        let boids = &mut self.boids[start as usize..end as usize];
+        boids.sort_unstable_by_key(|(pos, _)| child_of(*pos, center, dims));
+
+        let first_child = self.nodes.len();
+        let mut child_start = start;
+        for child in 0..(1 << dims) {
+            let child_len = self.boids[child_start as usize..end as usize]
+                .iter()
+                .take_while(|(pos, _)| child_of(*pos, center, dims) == child)
+                .count() as u32;
+            let (mut child_min, mut child_max) = (min, max);
+            for axis in 0..dims {
+                if child & (1 << axis) == 0 {
+                    child_max[axis] = center[axis];
+                } else {
+                    child_min[axis] = center[axis];
+                }
+            }
+            self.nodes.push(Node {
+                min: child_min,
+                max: child_max,
+                first_child: None,
+                start: child_start,
+                end: child_start + child_len,
+            });
+            child_start += child_len;
+        }
+        self.nodes[node_idx].first_child = Some(first_child as u32);
+
+        for child in first_child..first_child + (1 << dims) {
+            self.split(child, depth + 1);
+        }
+    }
+}
+
+impl Partition for Tree {
+    #[inline(always)]
+    fn for_each_near(&self, pos: Vec3, radius: f32, mut f: impl FnMut(Vec3, Vec3)) {
+        if self.boids.is_empty() {
+            return;
+        }
+        let radius_sq = radius * radius;
+        let mut stack = [0u32; STACK_SIZE];
+        let mut len = 1;
+        while len > 0 {
+            len -= 1;
+            let node = &self.nodes[stack[len] as usize];
+            if pos.clamp(node.min, node.max).distance_squared(pos) > radius_sq {
+                continue;
+            }
+            match node.first_child {
+                Some(first_child) => {
+                    for child in first_child..first_child + (1 << self.dims) {
+                        stack[len] = child;
+                        len += 1;
+                    }
+                }
+                None => {
+                    for (pos, vel) in &self.boids[node.start as usize..node.end as usize] {
+                        f(*pos, *vel);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[inline(always)]
+fn child_of(pos: Vec3, center: Vec3, dims: usize) -> usize {
+    (0..dims).fold(0, |child, axis| {
+        child | (((pos[axis] >= center[axis]) as usize) << axis)
+    })
+}
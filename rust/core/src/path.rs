@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use glam::*;
+
+use crate::{steer_towards, BoidProperties};
+
+/// What boids do at the ends of a `Path`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathMode {
+    #[default]
+    /// The path is closed, boids go from the last point back to the first one.
+    Loop,
+    /// Boids turn around at the ends of the path. Each boid goes along the path in the direction it's moving in.
+    PingPong,
+}
+
+/// A polyline (eg. the baked points of a curve) a flock follows along.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Path {
+    /// The points of the path, in the space of the path (see `with_transform`).
+    /// Shared, so the path can be cloned every tick without copying the points.
+    pub points: Arc<[Vec3]>,
+    pub mode: PathMode,
+    /// How far from the path boids can stray before they steer back onto it.
+    pub radius: f32,
+    /// How far ahead (in seconds, at their current velocity) boids predict their position to check if they're straying.
+    pub predict: f32,
+    /// How strong this path is compared to the targets of the flock.
+    pub weight: f32,
+    /// Transforms positions from the space of the path into the space of the flock, set with `with_transform`.
+    /// Paths are expected not to be scaled non-uniformly, as boids are projected onto the path in its own space.
+    from_path: Affine3A,
+    /// The inverse of `from_path`, so it isn't calculated again for every boid.
+    to_path: Affine3A,
+}
+
+impl Path {
+    pub fn new(points: impl Into<Arc<[Vec3]>>, mode: PathMode) -> Self {
+        Self {
+            points: points.into(),
+            mode,
+            radius: 20.0,
+            predict: 0.5,
+            weight: 1.0,
+            from_path: Affine3A::IDENTITY,
+            to_path: Affine3A::IDENTITY,
+        }
+    }
+
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_predict(mut self, predict: f32) -> Self {
+        self.predict = predict;
+        self
+    }
+
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_transform(mut self, from_path: Affine3A) -> Self {
+        self.from_path = from_path;
+        self.to_path = from_path.inverse();
+        self
+    }
+
+    #[inline(always)]
+    fn segment_count(&self) -> usize {
+        match (self.points.len(), self.mode) {
+            (0 | 1, _) => 0,
+            (len, PathMode::Loop) => len,
+            (len, PathMode::PingPong) => len - 1,
+        }
+    }
+
+    #[inline(always)]
+    fn segment(&self, index: usize) -> (Vec3, Vec3) {
+        (
+            self.points[index],
+            self.points[(index + 1) % self.points.len()],
+        )
+    }
+
+    /// Returns the index of the segment closest to `pos`, how far along it (from 0 to 1) the closest point is, and that point.
+    #[inline(always)]
+    fn project(&self, pos: Vec3) -> (usize, f32, Vec3) {
+        let mut closest = (0, 0.0, self.points[0]);
+        let mut closest_dist = f32::INFINITY;
+        for index in 0..self.segment_count() {
+            let (start, end) = self.segment(index);
+            let along = end - start;
+            let t = (pos - start).dot(along) / along.length_squared().max(f32::EPSILON);
+            let t = t.clamp(0.0, 1.0);
+            let point = start + along * t;
+            let dist = pos.distance_squared(point);
+            // on ties (where segments meet) the later segment wins, boids are going on to it
+            if dist <= closest_dist {
+                closest_dist = dist;
+                closest = (index, t, point);
+            }
+        }
+        closest
+    }
+
+    /// Calculates the (weighted) force that keeps a boid going along this path.
+    /// Boids predicted to leave the path radius steer back onto it, others steer along it.
+    #[inline(always)]
+    pub fn force(&self, boid_pos: Vec3, boid_vel: Vec3, boid_props: &BoidProperties) -> Vec3 {
+        let segments = self.segment_count();
+        if segments == 0 {
+            return Vec3::ZERO;
+        }
+        let predicted = boid_pos + boid_vel * self.predict;
+        let (segment, t, point) = self.project(self.to_path.transform_point3(predicted));
+        let (start, end) = self.segment(segment);
+        let point = self.from_path.transform_point3(point);
+        let tangent = self
+            .from_path
+            .transform_vector3(end - start)
+            .normalize_or_zero();
+        let direction = match self.mode {
+            PathMode::Loop => 1.0,
+            PathMode::PingPong if segment == segments - 1 && t >= 1.0 => -1.0,
+            PathMode::PingPong if segment == 0 && t <= 0.0 => 1.0,
+            PathMode::PingPong if boid_vel.dot(tangent) < 0.0 => -1.0,
+            PathMode::PingPong => 1.0,
+        };
+        let desired = if predicted.distance_squared(point) > self.radius * self.radius {
+            // aim a bit ahead of the closest point, so boids don't stall when coming back
+            point + tangent * direction * self.radius - boid_pos
+        } else {
+            tangent * direction
+        };
+        steer_towards(
+            desired,
+            boid_vel,
+            boid_props.max_speed,
+            boid_props.max_force,
+        ) * self.weight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boids_follow_and_turn_around() {
+        let props = BoidProperties::default();
+        let points = vec![Vec3::ZERO, vec3(100.0, 0.0, 0.0), vec3(100.0, 100.0, 0.0)];
+        let path = Path::new(points, PathMode::PingPong).with_radius(10.0);
+
+        // on the path, going along it
+        let force = path.force(vec3(50.0, 0.0, 0.0), Vec3::X * 10.0, &props);
+        assert!(force.x > 0.0 && force.y == 0.0);
+        // off the path, steering back onto it
+        let force = path.force(vec3(50.0, -50.0, 0.0), Vec3::ZERO, &props);
+        assert!(force.y > 0.0);
+        // at the end, turning around
+        let force = path.force(vec3(100.0, 100.0, 0.0), Vec3::Y * 10.0, &props);
+        assert!(force.y < 0.0);
+
+        // looping from the end back to the start
+        let path = Path {
+            mode: PathMode::Loop,
+            ..path
+        };
+        let force = path.force(vec3(100.0, 100.0, 0.0), Vec3::Y * 10.0, &props);
+        assert!(force.x < 0.0 && force.y < 0.0);
+
+        // the same path, moved and turned by its transform instead of its points
+        let from_path = Affine3A::from_rotation_translation(
+            Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+            vec3(50.0, 20.0, 0.0),
+        );
+        let moved = Path::new(
+            path.points
+                .iter()
+                .map(|p| from_path.transform_point3(*p))
+                .collect::<Vec<_>>(),
+            PathMode::Loop,
+        )
+        .with_radius(10.0);
+        let transformed = path.with_transform(from_path);
+        for (pos, vel) in [
+            (vec3(30.0, 60.0, 0.0), Vec3::Y),
+            (vec3(-90.0, 0.0, 0.0), Vec3::X),
+        ] {
+            let expected = moved.force(pos, vel, &props);
+            assert!(transformed
+                .force(pos, vel, &props)
+                .abs_diff_eq(expected, 1e-3));
+        }
+    }
+}
@@ -0,0 +1,117 @@
+/// Properties for a boid.
+///
+/// Note that these properties are used as is and do not get scaled / translated between 2D / 3D units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoidProperties {
+    /// Max speed of this boid, in units per second.
+    pub max_speed: f32,
+    /// Max force (acceleration) that will be applied to this boid, in units per second squared.
+    pub max_force: f32,
+    /// Min speed of this boid, in units per second. Boids that are moving never go slower than this.
+    pub min_speed: f32,
+    /// Drag that slows this boid down proportionally to its speed.
+    pub linear_drag: f32,
+    /// Drag that slows this boid down proportionally to its speed squared.
+    pub quadratic_drag: f32,
+    /// How much to align with other boids.
+    pub alignment: f32,
+    /// How much to cohere to other boids.
+    pub cohesion: f32,
+    /// How much to seperate from other boids.
+    pub seperation: f32,
+    /// How much to follow the targets, path or leader of the flock (if there are any).
+    pub targeting: f32,
+    /// Distance from an arrive target at which boids start slowing down.
+    pub arrive_slowing_radius: f32,
+    /// Distance from an arrive target at which boids want to stop completely.
+    pub arrive_stop_distance: f32,
+    /// How much to avoid obstacles.
+    pub avoidance: f32,
+    /// How much to go along with flow fields.
+    pub flow: f32,
+    /// How much to wander around.
+    pub wander_strength: f32,
+    /// Radius of the circle the wander point moves on. The circle is one second ahead of the boid, at its current velocity.
+    /// The larger this is compared to the speed of the boid, the sharper it turns while wandering.
+    pub wander_radius: f32,
+    /// How far the wander point moves randomly per second.
+    pub wander_jitter: f32,
+}
+
+impl Default for BoidProperties {
+    fn default() -> Self {
+        Self {
+            max_speed: 240.0,
+            max_force: 3600.0,
+            min_speed: 0.0,
+            linear_drag: 0.0,
+            quadratic_drag: 0.0,
+            alignment: 1.5,
+            cohesion: 1.0,
+            seperation: 1.2,
+            targeting: 0.8,
+            arrive_slowing_radius: 100.0,
+            arrive_stop_distance: 0.0,
+            avoidance: 2.0,
+            flow: 1.0,
+            wander_strength: 0.0,
+            wander_radius: 120.0,
+            wander_jitter: 240.0,
+        }
+    }
+}
+
+/// Properties for a flock.
+///
+/// Note that these properties are used as is and do not get scaled / translated between 2D / 3D units.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlockProperties {
+    /// Distance (squared) to apply seperation force between boids in a flock.
+    pub goal_seperation: f32,
+    /// Distance (squared) to apply alignment force between boids in a flock.
+    pub goal_alignment: f32,
+    /// Distance (squared) to apply cohesion force between boids in a flock.
+    pub goal_cohesion: f32,
+    /// Field of view (in degrees) in which neighbours are considered for seperation.
+    pub fov_seperation: f32,
+    /// Field of view (in degrees) in which neighbours are considered for alignment.
+    pub fov_alignment: f32,
+    /// Field of view (in degrees) in which neighbours are considered for cohesion.
+    pub fov_cohesion: f32,
+}
+
+impl Default for FlockProperties {
+    fn default() -> Self {
+        Self {
+            goal_seperation: 625.0,
+            goal_alignment: 2500.0,
+            goal_cohesion: 2500.0,
+            fov_seperation: 360.0,
+            fov_alignment: 360.0,
+            fov_cohesion: 360.0,
+        }
+    }
+}
+
+impl FlockProperties {
+    /// The largest distance (not squared) at which boids in this flock affect each other.
+    pub fn max_radius(&self) -> f32 {
+        self.goal_seperation
+            .max(self.goal_alignment)
+            .max(self.goal_cohesion)
+            .max(0.0)
+            .sqrt()
+    }
+
+    /// Cosine of half of a field of view given in degrees.
+    ///
+    /// A neighbour is visible if the cosine of the angle between the boid's heading
+    /// and the direction to the neighbour is at least this value.
+    pub fn fov_cos(fov: f32) -> f32 {
+        if fov >= 360.0 {
+            -1.0
+        } else {
+            (fov.max(0.0) * 0.5).to_radians().cos()
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use glam::*;
+
+use crate::{steer_towards, BoidProperties, Partition};
+
+/// How the boids of a flock react to the boids of another flock.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RelationMode {
+    #[default]
+    /// Don't react to the other flock.
+    Ignore,
+    /// Keep some distance from nearby boids of the other flock, like boids do within their flock.
+    Separate,
+    /// Flee from the closest nearby boid of the other flock.
+    Flee,
+    /// Chase the closest nearby boid of the other flock, aiming for where it's going to be.
+    Chase,
+}
+
+/// Declares how the boids of the flock at index `flock` react to the boids of the flock at index `other`,
+/// in the `flocks` of a `World`. Relations are one way, `other` doesn't react to `flock` unless it has its own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Relation {
+    pub flock: usize,
+    pub other: usize,
+    pub mode: RelationMode,
+    /// Distance at which boids of `other` are noticed.
+    pub radius: f32,
+    /// How strong the reaction is, compared to the other forces acting on a boid.
+    pub weight: f32,
+    /// Transforms positions from the space of `other` into the space of `flock`, set with `with_transform`.
+    /// Flocks are expected not to be scaled relative to each other, as `radius` isn't scaled.
+    to_flock: Affine3A,
+    /// The inverse of `to_flock`, so it isn't calculated again for every boid.
+    to_other: Affine3A,
+}
+
+impl Relation {
+    pub fn new(flock: usize, other: usize, mode: RelationMode) -> Self {
+        Self {
+            flock,
+            other,
+            mode,
+            radius: 100.0,
+            weight: 1.0,
+            to_flock: Affine3A::IDENTITY,
+            to_other: Affine3A::IDENTITY,
+        }
+    }
+
+    pub fn with_radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_transform(mut self, to_flock: Affine3A) -> Self {
+        self.to_flock = to_flock;
+        self.to_other = to_flock.inverse();
+        self
+    }
+
+    /// Calculates the (weighted) force a boid of `flock` feels from the boids of `other` (queried through `others`).
+    #[inline(always)]
+    pub fn force<P: Partition + ?Sized>(
+        &self,
+        boid_pos: Vec3,
+        boid_vel: Vec3,
+        boid_props: &BoidProperties,
+        others: &P,
+    ) -> Vec3 {
+        if self.mode == RelationMode::Ignore || self.radius <= 0.0 {
+            return Vec3::ZERO;
+        }
+        let radius_squared = self.radius * self.radius;
+        let query_pos = self.to_other.transform_point3(boid_pos);
+
+        let mut away = Vec3::ZERO;
+        let mut away_count = 0;
+        let mut closest = None;
+        let mut closest_dist = radius_squared;
+        others.for_each_near(query_pos, self.radius, |other_pos, other_vel| {
+            let other_pos = self.to_flock.transform_point3(other_pos);
+            let dist = boid_pos.distance_squared(other_pos);
+            if dist >= radius_squared || dist <= f32::EPSILON {
+                return;
+            }
+            away += (boid_pos - other_pos).normalize() / dist.sqrt();
+            away_count += 1;
+            if dist < closest_dist {
+                closest_dist = dist;
+                closest = Some((other_pos, self.to_flock.transform_vector3(other_vel)));
+            }
+        });
+
+        let max_speed = boid_props.max_speed;
+        let max_force = boid_props.max_force;
+        let force = match (self.mode, closest) {
+            (_, None) | (RelationMode::Ignore, _) => return Vec3::ZERO,
+            (RelationMode::Separate, _) => {
+                steer_towards(away / away_count as f32, boid_vel, max_speed, max_force)
+            }
+            (RelationMode::Flee, Some((other_pos, _))) => {
+                steer_towards(boid_pos - other_pos, boid_vel, max_speed, max_force)
+            }
+            (RelationMode::Chase, Some((other_pos, other_vel))) => {
+                // lead the target by the time it takes to get to it
+                let lead = closest_dist.sqrt() / max_speed.max(f32::EPSILON);
+                let predicted = other_pos + other_vel * lead;
+                steer_towards(predicted - boid_pos, boid_vel, max_speed, max_force)
+            }
+        };
+        force * self.weight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prey_flees_and_predators_chase() {
+        let props = BoidProperties::default();
+        // another flock, offset by 100 along X, with one boid moving along +Y
+        let others = [(Vec3::ZERO, vec3(0.0, 100.0, 0.0))];
+        let to_flock = Affine3A::from_translation(vec3(100.0, 0.0, 0.0));
+        let flee = Relation::new(0, 1, RelationMode::Flee).with_transform(to_flock);
+        let force = flee.force(vec3(80.0, 0.0, 0.0), Vec3::ZERO, &props, &others[..]);
+        assert!(force.x < 0.0 && force.y == 0.0);
+
+        let chase = Relation::new(0, 1, RelationMode::Chase)
+            .with_transform(to_flock)
+            .with_weight(2.0);
+        let force = chase.force(vec3(80.0, 0.0, 0.0), Vec3::ZERO, &props, &others[..]);
+        assert!(force.x > 0.0 && force.y > 0.0);
+        assert!((force.length() - props.max_speed * 2.0).abs() < 1e-3);
+
+        let out_of_range = Relation::new(0, 1, RelationMode::Flee).with_transform(to_flock);
+        let force = out_of_range.force(vec3(-80.0, 0.0, 0.0), Vec3::ZERO, &props, &others[..]);
+        assert_eq!(force, Vec3::ZERO);
+    }
+}
@@ -0,0 +1,45 @@
+use glam::*;
+
+/// A small seedable random number generator (SplitMix64).
+/// Flocks carry their own, so a simulation can be reproduced from the same seed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Creates a generator seeded from this one, eg. to give every flock its own sequence from a single seed.
+    pub fn fork(&mut self) -> Self {
+        Self::new(self.next_u64())
+    }
+
+    #[inline(always)]
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number between 0 (inclusive) and 1 (exclusive).
+    #[inline(always)]
+    pub fn next_f32(&mut self) -> f32 {
+        // the top 24 bits fill the mantissa exactly
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Returns a random vector with every axis between -1 and 1.
+    /// The Z axis is zero for 2 `dimensions`.
+    #[inline(always)]
+    pub fn next_vec3(&mut self, dimensions: usize) -> Vec3 {
+        let mut axis = || self.next_f32() * 2.0 - 1.0;
+        let (x, y) = (axis(), axis());
+        let z = if dimensions == 2 { 0.0 } else { axis() };
+        vec3(x, y, z)
+    }
+}
@@ -0,0 +1,106 @@
+use glam::*;
+
+use crate::Boid;
+
+/// The boids of a flock, stored as a structure of arrays.
+/// All arrays have the same length, the boid at index `i` is made up of the `i`th element of each.
+#[derive(Clone, Debug, Default)]
+pub struct BoidStorage {
+    pub positions: Vec<Vec3>,
+    pub velocities: Vec<Vec3>,
+    /// Index of each boid's properties in the `boid_properties` of its flock.
+    pub properties: Vec<usize>,
+    /// See `Boid::external_repulsion`.
+    pub external_repulsion: Vec<Vec3>,
+    /// See `Boid::wander`.
+    pub wander: Vec<Vec3>,
+    /// How much the velocity of each boid changed per second in the last step.
+    pub accelerations: Vec<Vec3>,
+}
+
+impl BoidStorage {
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Returns a copy of the boid at `index`.
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> Boid {
+        Boid {
+            position: self.positions[index],
+            velocity: self.velocities[index],
+            properties: self.properties[index],
+            external_repulsion: self.external_repulsion[index],
+            wander: self.wander[index],
+        }
+    }
+
+    pub fn push(&mut self, boid: Boid) {
+        self.positions.push(boid.position);
+        self.velocities.push(boid.velocity);
+        self.properties.push(boid.properties);
+        self.external_repulsion.push(boid.external_repulsion);
+        self.wander.push(boid.wander);
+        self.accelerations.push(Vec3::ZERO);
+    }
+
+    /// Removes the boid at `index`, replacing it with the last boid.
+    pub fn swap_remove(&mut self, index: usize) -> Boid {
+        self.accelerations.swap_remove(index);
+        Boid {
+            position: self.positions.swap_remove(index),
+            velocity: self.velocities.swap_remove(index),
+            properties: self.properties.swap_remove(index),
+            external_repulsion: self.external_repulsion.swap_remove(index),
+            wander: self.wander.swap_remove(index),
+        }
+    }
+
+    /// Removes all boids past the first `len`.
+    pub fn truncate(&mut self, len: usize) {
+        self.positions.truncate(len);
+        self.velocities.truncate(len);
+        self.properties.truncate(len);
+        self.external_repulsion.truncate(len);
+        self.wander.truncate(len);
+        self.accelerations.truncate(len);
+    }
+
+    /// Iterates over copies of all boids.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = Boid> + '_ {
+        (0..self.len()).map(|index| self.get(index))
+    }
+}
+
+impl Extend<Boid> for BoidStorage {
+    fn extend<T: IntoIterator<Item = Boid>>(&mut self, iter: T) {
+        for boid in iter {
+            self.push(boid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swap_remove_keeps_boids_together() {
+        let mut storage = BoidStorage::default();
+        storage.extend((0..3).map(|i| Boid::new(Vec3::splat(i as f32), Vec3::X * i as f32, i)));
+
+        let removed = storage.swap_remove(0);
+        assert_eq!(removed.position, Vec3::ZERO);
+        assert_eq!(storage.len(), 2);
+        let moved = storage.get(0);
+        assert_eq!(moved.position, Vec3::splat(2.0));
+        assert_eq!(moved.velocity, Vec3::X * 2.0);
+        assert_eq!(moved.properties, 2);
+    }
+}
@@ -0,0 +1,123 @@
+use glam::*;
+
+use crate::{steer_towards, BoidProperties};
+
+/// How boids react to a `Target`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TargetMode {
+    #[default]
+    /// Move towards the target.
+    Seek,
+    /// Move away from the target.
+    Flee,
+    /// Move towards the target, slowing down when getting close to it.
+    /// See `BoidProperties::arrive_slowing_radius` and `BoidProperties::arrive_stop_distance`.
+    Arrive,
+}
+
+/// A position a flock is drawn to (or pushed away from).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target {
+    pub position: Vec3,
+    pub mode: TargetMode,
+    /// How strong this target is compared to other targets of the flock.
+    pub weight: f32,
+    /// Distance at which this target stops affecting boids, the force fades out linearly until then.
+    /// Zero or less means the target affects boids at any distance.
+    pub falloff_radius: f32,
+}
+
+impl Target {
+    pub fn new(position: Vec3, mode: TargetMode) -> Self {
+        Self {
+            position,
+            mode,
+            weight: 1.0,
+            falloff_radius: 0.0,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    pub fn with_falloff_radius(mut self, falloff_radius: f32) -> Self {
+        self.falloff_radius = falloff_radius;
+        self
+    }
+
+    /// Calculates the (weighted) force this target applies to a boid.
+    #[inline(always)]
+    pub fn force(&self, boid_pos: Vec3, boid_vel: Vec3, boid_props: &BoidProperties) -> Vec3 {
+        let max_speed = boid_props.max_speed;
+        let max_force = boid_props.max_force;
+        let offset = self.position - boid_pos;
+        let falloff = if self.falloff_radius > 0.0 {
+            1.0 - offset.length() / self.falloff_radius
+        } else {
+            1.0
+        };
+        if falloff <= 0.0 {
+            return Vec3::ZERO;
+        }
+        let force = match self.mode {
+            TargetMode::Seek => steer_towards(offset, boid_vel, max_speed, max_force),
+            TargetMode::Flee => steer_towards(-offset, boid_vel, max_speed, max_force),
+            TargetMode::Arrive => {
+                let desired =
+                    offset.normalize_or_zero() * arrive_speed(offset.length(), boid_props);
+                (desired - boid_vel).clamp_length_max(max_force)
+            }
+        };
+        force * self.weight * falloff
+    }
+}
+
+/// The speed a boid arriving at a target that is `dist` away should have.
+#[inline(always)]
+fn arrive_speed(dist: f32, boid_props: &BoidProperties) -> f32 {
+    let stop = boid_props.arrive_stop_distance;
+    if dist <= stop {
+        return 0.0;
+    }
+    let slowing = (boid_props.arrive_slowing_radius - stop).max(f32::EPSILON);
+    boid_props.max_speed * ((dist - stop) / slowing).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Boid;
+
+    #[test]
+    fn flee_fades_out_at_falloff_radius() {
+        let props = BoidProperties::default();
+        let target = Target::new(Vec3::ZERO, TargetMode::Flee).with_falloff_radius(100.0);
+        let near = target.force(vec3(25.0, 0.0, 0.0), Vec3::ZERO, &props);
+        let far = target.force(vec3(50.0, 0.0, 0.0), Vec3::ZERO, &props);
+        assert_eq!(near, vec3(180.0, 0.0, 0.0));
+        assert_eq!(far, vec3(120.0, 0.0, 0.0));
+        assert_eq!(
+            target.force(vec3(150.0, 0.0, 0.0), Vec3::ZERO, &props),
+            Vec3::ZERO
+        );
+    }
+
+    #[test]
+    fn arrive_settles_on_target() {
+        let props = BoidProperties {
+            arrive_slowing_radius: 100.0,
+            arrive_stop_distance: 10.0,
+            ..Default::default()
+        };
+        let target = Target::new(vec3(500.0, 0.0, 0.0), TargetMode::Arrive);
+        let mut boid = Boid::new(Vec3::ZERO, Vec3::ZERO, 0);
+        for _ in 0..900 {
+            let force = target.force(boid.position, boid.velocity, &props);
+            boid.apply_force(&props, force, 1.0 / 60.0);
+        }
+        assert!(boid.position.distance(target.position) < 20.0);
+        assert!(boid.velocity.length() < 1.0);
+    }
+}
@@ -0,0 +1,257 @@
+use glam::*;
+use rayon::prelude::*;
+
+use crate::{Environment, Flock, FlockPartition, Partitioning, Relation, Rng};
+
+/// A set of flocks that are simulated together.
+/// Stepping is deterministic: forces are calculated per boid and collected in order no matter how rayon splits
+/// the work, so the same world (with the same seeds) gives bit-identical results on any number of threads.
+/// Across platforms results only stay bit-identical without the `fast-math` feature, which switches glam to platform specific code paths,
+/// and as long as the platforms' `cos` and `atan2` (used for field of view and 2D orientation) round the same way.
+#[derive(Clone, Debug, Default)]
+pub struct World {
+    pub flocks: Vec<Flock>,
+    pub environment: Environment,
+    /// How flocks react to the boids of other flocks.
+    pub relations: Vec<Relation>,
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reseeds the rng of every flock, each with its own sequence derived from `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        let mut rng = Rng::new(seed);
+        for flock in &mut self.flocks {
+            flock.rng = rng.fork();
+        }
+    }
+
+    /// Calculates the forces to apply to every boid of every flock.
+    /// The result is indexed the same way as `flocks` and their `boids`.
+    pub fn forces(&self) -> Vec<Vec<Vec3>> {
+        let partitions: Vec<FlockPartition> =
+            self.flocks.par_iter().map(Flock::partition).collect();
+        // the grid of a flock is sized for its own radius, which can be far off from the radius of a relation
+        let relation_partitions: Vec<Option<FlockPartition>> = self
+            .relations
+            .par_iter()
+            .map(|relation| {
+                let other = self.flocks.get(relation.other)?;
+                (other.partitioning == Partitioning::Grid)
+                    .then(|| other.partition_with_cell_size(relation.radius))
+            })
+            .collect();
+        self.flocks
+            .par_iter()
+            .zip(partitions.par_iter())
+            .enumerate()
+            .map(|(index, (flock, partition))| {
+                let mut forces = flock.forces(partition, &self.environment);
+                let relations = self.relations.iter().zip(&relation_partitions);
+                for (relation, relation_partition) in relations.filter(|(r, _)| r.flock == index) {
+                    let Some(others) = relation_partition
+                        .as_ref()
+                        .or_else(|| partitions.get(relation.other))
+                    else {
+                        continue;
+                    };
+                    forces.par_iter_mut().enumerate().for_each(|(i, force)| {
+                        let boid = flock.boids.get(i);
+                        let props = &flock.boid_properties[boid.properties];
+                        *force += relation.force(boid.position, boid.velocity, props, others);
+                    });
+                }
+                forces
+            })
+            .collect()
+    }
+
+    /// Advances the simulation of all flocks by `delta` seconds.
+    pub fn step(&mut self, delta: f32) {
+        for flock in &mut self.flocks {
+            flock.wander(delta);
+        }
+        let forces = self.forces();
+        for (flock, forces) in self.flocks.iter_mut().zip(forces) {
+            flock.apply_forces(&forces, delta);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Boid, BoidProperties, FlockProperties, RelationMode, Target, TargetMode};
+
+    #[test]
+    fn flock_moves_towards_target() {
+        let mut flock = Flock::new_2d(FlockProperties::default());
+        flock
+            .targets
+            .push(Target::new(vec3(1000.0, 0.0, 0.0), TargetMode::Seek));
+        flock.boid_properties.push(BoidProperties::default());
+        flock
+            .boids
+            .extend((0..10).map(|i| Boid::new(vec3(0.0, i as f32 * 10.0, 0.0), Vec3::ZERO, 0)));
+        let mut world = World::new();
+        world.flocks.push(flock);
+
+        let centroid = |world: &World| {
+            world.flocks[0]
+                .boids
+                .iter()
+                .map(|b| b.position)
+                .sum::<Vec3>()
+                / 10.0
+        };
+        let start = centroid(&world);
+        for _ in 0..300 {
+            world.step(1.0 / 60.0);
+        }
+        let end = centroid(&world);
+
+        assert!(end.x > start.x + 100.0);
+        assert!(world.flocks[0].boids.iter().all(|b| b.position.z == 0.0));
+    }
+
+    #[test]
+    fn zero_delta_leaves_boids_in_place() {
+        let mut flock = Flock::new_2d(FlockProperties::default());
+        flock.boid_properties.push(BoidProperties::default());
+        flock.boids.push(Boid::new(Vec3::ZERO, Vec3::X * 100.0, 0));
+        flock.boids.push(Boid::new(Vec3::Y * 10.0, Vec3::ZERO, 0));
+        let mut world = World::new();
+        world.flocks.push(flock);
+        let before = world.flocks[0].boids.positions.clone();
+
+        world.step(0.0);
+        assert_eq!(world.flocks[0].boids.positions, before);
+        assert!(world.flocks[0]
+            .boids
+            .accelerations
+            .iter()
+            .all(|a| a.is_finite()));
+    }
+
+    #[test]
+    fn wander_is_reproducible() {
+        let props = BoidProperties {
+            wander_strength: 1.0,
+            ..Default::default()
+        };
+        let run = |seed| {
+            let mut flock = Flock::new_3d(FlockProperties::default());
+            flock.rng = Rng::new(seed);
+            flock.boid_properties.push(props);
+            flock.boids.push(Boid::new(Vec3::ZERO, Vec3::X * 100.0, 0));
+            let mut world = World::new();
+            world.flocks.push(flock);
+            for _ in 0..120 {
+                world.step(1.0 / 60.0);
+            }
+            world.flocks[0].boids.get(0)
+        };
+
+        let boid = run(7);
+        assert_eq!(boid.position, run(7).position);
+        assert_ne!(boid.position, run(8).position);
+        // without neighbours or a target, only wandering turns the boid
+        assert!(boid.velocity.normalize().dot(Vec3::X) < 0.999);
+    }
+
+    #[test]
+    fn relations_reach_past_the_grid_of_the_other_flock() {
+        let mut spawn = Rng::new(3);
+        let mut flock = |props| {
+            let mut flock = Flock::new_3d(props);
+            flock.boid_properties.push(BoidProperties::default());
+            flock.boids.extend(
+                (0..500).map(|_| Boid::new(spawn.next_vec3(3) * 100.0, spawn.next_vec3(3), 0)),
+            );
+            flock
+        };
+        let mut world = World::new();
+        world.flocks.push(flock(FlockProperties::default()));
+        // sharks that don't flock, so their grid would have zero sized cells
+        world.flocks.push(flock(FlockProperties {
+            goal_seperation: 0.0,
+            goal_alignment: 0.0,
+            goal_cohesion: 0.0,
+            ..Default::default()
+        }));
+        for (mode, radius) in [(RelationMode::Flee, 80.0), (RelationMode::Separate, 0.004)] {
+            world.relations = vec![Relation::new(0, 1, mode).with_radius(radius)];
+            let flock = &world.flocks[0];
+            let alone = flock.forces(&flock.partition(), &world.environment);
+            let forces = world.forces();
+            let others: Vec<(Vec3, Vec3)> = world.flocks[1]
+                .boids
+                .iter()
+                .map(|boid| (boid.position, boid.velocity))
+                .collect();
+            let props = BoidProperties::default();
+            for (i, (force, alone)) in forces[0].iter().zip(alone).enumerate() {
+                let boid = world.flocks[0].boids.get(i);
+                let brute = world.relations[0].force(
+                    boid.position,
+                    boid.velocity,
+                    &props,
+                    others.as_slice(),
+                );
+                assert!((*force - alone).abs_diff_eq(brute, 1e-3));
+            }
+        }
+    }
+
+    #[test]
+    fn steps_are_identical_on_any_thread_count() {
+        let props = BoidProperties {
+            wander_strength: 0.5,
+            ..Default::default()
+        };
+        let run = |threads| {
+            let mut spawn = Rng::new(1);
+            let mut world = World::new();
+            for partitioning in [Partitioning::Grid, Partitioning::Tree] {
+                let mut flock = Flock::new_3d(FlockProperties::default());
+                flock.partitioning = partitioning;
+                flock.boid_properties.push(props);
+                flock
+                    .targets
+                    .push(Target::new(Vec3::splat(200.0), TargetMode::Arrive));
+                flock.boids.extend((0..500).map(|_| {
+                    let position = spawn.next_vec3(3) * 300.0;
+                    Boid::new(position, spawn.next_vec3(3) * 50.0, 0)
+                }));
+                world.flocks.push(flock);
+            }
+            world
+                .relations
+                .push(Relation::new(0, 1, RelationMode::Chase));
+            world.reseed(42);
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            pool.install(|| {
+                for _ in 0..60 {
+                    world.step(1.0 / 60.0);
+                }
+            });
+            world
+                .flocks
+                .iter()
+                .flat_map(|flock| flock.boids.iter())
+                .flat_map(|boid| [boid.position, boid.velocity])
+                .flat_map(|v| v.to_array().map(f32::to_bits))
+                .collect::<Vec<_>>()
+        };
+
+        let single = run(1);
+        assert_eq!(single, run(4));
+        assert_eq!(single, run(4));
+    }
+}
@@ -1,6 +1,6 @@
 use super::*;
 
-use crate::{BoidProperties, Flock2D};
+use crate::{get_singleton, BoidProperties, Flock2D};
 
 #[derive(GodotClass)]
 #[class(init, base=Node2D)]
@@ -8,10 +8,20 @@ use crate::{BoidProperties, Flock2D};
 /// Doesn't do anything on it's own, must be a child of a `Flock2D`.
 pub struct Boid2D {
     #[export]
+    #[var(get, set = set_properties)]
     /// The properties of this boid.
-    /// Note: this cannot be changed in runtime, aside from removing and readding the node.
+    /// Changes to the resource, or setting a different one, apply from the next physics tick.
     properties: Option<Gd<BoidProperties>>,
-    props: BoidProperties,
+    #[export]
+    #[var(get, set = set_align_to_velocity)]
+    /// Whether to rotate this boid to face the direction it's moving in.
+    align_to_velocity: bool,
+    #[export(range = (0.0, 1080.0, degrees, or_greater))]
+    #[var(get, set = set_max_turn_rate)]
+    /// How fast this boid can turn to face its velocity, in degrees per second. Zero turns instantly.
+    max_turn_rate: f32,
+    /// Velocity of this boid while it's not in a flock.
+    /// Boids in a flock have their velocity stored in the `Boids` singleton.
     vel: Vec2,
     flock_id: Option<InstanceId>,
     base: Base<Node2D>,
@@ -21,17 +31,57 @@ pub struct Boid2D {
 impl Boid2D {
     #[func]
     #[inline(always)]
-    /// Get the current velocity of this boid.
+    /// Get the current velocity of this boid, in units per second.
     fn get_velocity(&self) -> Vector2 {
-        Vector2::new(self.vel.x, self.vel.y)
+        let vel = self
+            .flock_id
+            .and_then(|flock_id| {
+                get_singleton()
+                    .bind()
+                    .get_boid_velocity_2d(flock_id, self.get_id())
+            })
+            .map_or(self.vel, |vel| vel.xy());
+        Vector2::new(vel.x, vel.y)
     }
 
     #[func]
     #[inline(always)]
-    /// Set the current velocity of this boid.
+    /// Set the current velocity of this boid, in units per second.
     fn set_velocity(&mut self, new_velocity: Vector2) {
         self.vel.x = new_velocity.x;
         self.vel.y = new_velocity.y;
+        if let Some(flock_id) = self.flock_id {
+            get_singleton().bind_mut().set_boid_velocity_2d(
+                flock_id,
+                self.get_id(),
+                self.vel.extend(0.0),
+            );
+        }
+    }
+
+    #[func]
+    /// Set the properties of this boid.
+    fn set_properties(&mut self, properties: Option<Gd<BoidProperties>>) {
+        self.properties = properties.clone();
+        if let Some(flock_id) = self.flock_id {
+            get_singleton()
+                .bind_mut()
+                .set_boid_properties_2d(flock_id, self.get_id(), properties);
+        }
+    }
+
+    #[func]
+    /// Set whether to rotate this boid to face the direction it's moving in.
+    fn set_align_to_velocity(&mut self, align_to_velocity: bool) {
+        self.align_to_velocity = align_to_velocity;
+        self.sync_orientation();
+    }
+
+    #[func]
+    /// Set how fast this boid can turn to face its velocity, in degrees per second.
+    fn set_max_turn_rate(&mut self, max_turn_rate: f32) {
+        self.max_turn_rate = max_turn_rate;
+        self.sync_orientation();
     }
 
     #[func]
@@ -52,7 +102,7 @@ impl Boid2D {
 #[godot_api]
 impl INode2D for Boid2D {
     fn enter_tree(&mut self) {
-        let Some(mut flock) = self
+        let Some(flock) = self
             .to_gd()
             .get_parent()
             .and_then(|gd| gd.try_cast::<Flock2D>().ok())
@@ -61,50 +111,69 @@ impl INode2D for Boid2D {
             godot_error!("[Boid2D:{boid_id}] boids parent isn't a Flock2D, or has no parent");
             return;
         };
-        let mut flock = flock.bind_mut();
-        flock.register_boid(self.get_id());
-        self.flock_id = Some(flock.get_id());
-    }
-
-    fn ready(&mut self) {
-        if let Some(props) = self.properties.as_ref() {
-            self.props = props.bind().clone();
-        }
+        let pos = self.base().get_position();
+        flock.bind().register_boid(
+            self.get_id(),
+            vec3(pos.x, pos.y, 0.0),
+            self.vel.extend(0.0),
+            self.properties.clone(),
+        );
+        self.flock_id = Some(flock.instance_id());
+        self.sync_orientation();
     }
 
     fn exit_tree(&mut self) {
-        let mut flock: Gd<Flock2D> = Gd::from_instance_id(self.get_flock_id());
-        flock.bind_mut().unregister_boid(self.get_id());
+        let flock: Gd<Flock2D> = Gd::from_instance_id(self.get_flock_id());
+        let boid = flock.bind().unregister_boid(self.get_id());
+        if let Some(boid) = boid {
+            self.vel = boid.velocity.xy();
+        }
     }
 }
 
-impl Boid for Boid2D {
+impl Boid2D {
+    /// How this boid turns to face its velocity, if it does.
     #[inline(always)]
-    fn apply_force(&mut self, force: Vec3) {
-        self.vel += force.xy();
-        self.vel = self.vel.clamp_length_max(self.props.max_speed);
-        let force_to_apply = Vector2::new(self.vel.x, self.vel.y);
-        self.base_mut().translate(force_to_apply);
+    fn get_orientation(&self) -> Option<boids_core::Orientation> {
+        self.align_to_velocity.then(|| boids_core::Orientation {
+            max_turn_rate: max_turn_rate(self.max_turn_rate),
+            ..Default::default()
+        })
     }
 
-    #[inline(always)]
-    fn get_boid_position(&self) -> Vec3 {
-        let pos = self.base().get_position();
-        vec3(pos.x, pos.y, 0.0)
-    }
-
-    #[inline(always)]
-    fn get_boid_velocity(&self) -> Vec3 {
-        vec3(self.vel.x, self.vel.y, 0.0)
+    /// Hands the orientation settings of this boid to the `Boids` singleton, if it's in a flock.
+    fn sync_orientation(&self) {
+        if let Some(flock_id) = self.flock_id {
+            get_singleton().bind_mut().set_boid_orientation_2d(
+                flock_id,
+                self.get_id(),
+                self.get_orientation(),
+            );
+        }
     }
+}
 
+impl Boid for Boid2D {
     #[inline(always)]
-    fn get_boid_properties(&self) -> &BoidProperties {
-        &self.props
+    fn get_boid_position(boid: &Gd<Self>) -> Vec3 {
+        let pos = boid.upcast_ref::<Node2D>().get_position();
+        vec3(pos.x, pos.y, 0.0)
     }
 
     #[inline(always)]
-    fn get_flock_id(&self) -> InstanceId {
-        self.get_flock_id()
+    fn set_boid_state(
+        boid: &mut Gd<Self>,
+        position: Vec3,
+        velocity: Vec3,
+        _acceleration: Vec3,
+        orientation: Option<boids_core::Orientation>,
+        delta: f32,
+    ) {
+        let node = boid.upcast_mut::<Node2D>();
+        node.set_position(Vector2::new(position.x, position.y));
+        if let Some(orientation) = orientation {
+            let rotation = orientation.rotation_2d(node.get_rotation(), velocity, delta);
+            node.set_rotation(rotation);
+        }
     }
 }
@@ -1,6 +1,6 @@
 use super::*;
 
-use crate::{to_glam_vec, BoidProperties, Flock3D};
+use crate::{get_singleton, to_glam_vec, BoidProperties, Flock3D};
 
 #[derive(GodotClass)]
 #[class(init, base=Node3D)]
@@ -8,10 +8,35 @@ use crate::{to_glam_vec, BoidProperties, Flock3D};
 /// Doesn't do anything on it's own, must be a child of a `Flock3D`.
 pub struct Boid3D {
     #[export]
+    #[var(get, set = set_properties)]
     /// The properties of this boid.
-    /// Note: this cannot be changed in runtime, aside from removing and readding the node.
+    /// Changes to the resource, or setting a different one, apply from the next physics tick.
     properties: Option<Gd<BoidProperties>>,
-    props: BoidProperties,
+    #[export]
+    #[var(get, set = set_align_to_velocity)]
+    /// Whether to rotate this boid to face the direction it's moving in, with its -Z axis (like `look_at`).
+    align_to_velocity: bool,
+    #[export(range = (0.0, 1080.0, degrees, or_greater))]
+    #[var(get, set = set_max_turn_rate)]
+    /// How fast this boid can turn to face its velocity, in degrees per second. Zero turns instantly.
+    max_turn_rate: f32,
+    #[export]
+    #[init(val = Vector3::UP)]
+    #[var(get, set = set_up_vector)]
+    /// Which way is up for this boid when it aligns to its velocity.
+    up_vector: Vector3,
+    #[export]
+    #[var(get, set = set_banking)]
+    /// How far this boid rolls into turns when it aligns to its velocity, in radians per unit of sideways acceleration.
+    /// Zero disables banking.
+    banking: f32,
+    #[export(range = (0.0, 90.0, degrees))]
+    #[init(val = 45.0)]
+    #[var(get, set = set_max_bank_angle)]
+    /// The largest angle this boid can bank by.
+    max_bank_angle: f32,
+    /// Velocity of this boid while it's not in a flock.
+    /// Boids in a flock have their velocity stored in the `Boids` singleton.
     vel: Vec3,
     flock_id: Option<InstanceId>,
     base: Base<Node3D>,
@@ -21,16 +46,75 @@ pub struct Boid3D {
 impl Boid3D {
     #[func]
     #[inline(always)]
-    /// Get the current velocity of this boid.
+    /// Get the current velocity of this boid, in units per second.
     fn get_velocity(&self) -> Vector3 {
-        Vector3::new(self.vel.x, self.vel.y, self.vel.z)
+        let vel = self
+            .flock_id
+            .and_then(|flock_id| {
+                get_singleton()
+                    .bind()
+                    .get_boid_velocity_3d(flock_id, self.get_id())
+            })
+            .unwrap_or(self.vel);
+        Vector3::new(vel.x, vel.y, vel.z)
     }
 
     #[func]
     #[inline(always)]
-    /// Set the current velocity of this boid.
+    /// Set the current velocity of this boid, in units per second.
     fn set_velocity(&mut self, new_velocity: Vector3) {
         self.vel = to_glam_vec(new_velocity);
+        if let Some(flock_id) = self.flock_id {
+            get_singleton()
+                .bind_mut()
+                .set_boid_velocity_3d(flock_id, self.get_id(), self.vel);
+        }
+    }
+
+    #[func]
+    /// Set the properties of this boid.
+    fn set_properties(&mut self, properties: Option<Gd<BoidProperties>>) {
+        self.properties = properties.clone();
+        if let Some(flock_id) = self.flock_id {
+            get_singleton()
+                .bind_mut()
+                .set_boid_properties_3d(flock_id, self.get_id(), properties);
+        }
+    }
+
+    #[func]
+    /// Set whether to rotate this boid to face the direction it's moving in.
+    fn set_align_to_velocity(&mut self, align_to_velocity: bool) {
+        self.align_to_velocity = align_to_velocity;
+        self.sync_orientation();
+    }
+
+    #[func]
+    /// Set how fast this boid can turn to face its velocity, in degrees per second.
+    fn set_max_turn_rate(&mut self, max_turn_rate: f32) {
+        self.max_turn_rate = max_turn_rate;
+        self.sync_orientation();
+    }
+
+    #[func]
+    /// Set which way is up for this boid when it aligns to its velocity.
+    fn set_up_vector(&mut self, up_vector: Vector3) {
+        self.up_vector = up_vector;
+        self.sync_orientation();
+    }
+
+    #[func]
+    /// Set how far this boid rolls into turns when it aligns to its velocity.
+    fn set_banking(&mut self, banking: f32) {
+        self.banking = banking;
+        self.sync_orientation();
+    }
+
+    #[func]
+    /// Set the largest angle this boid can bank by, in degrees.
+    fn set_max_bank_angle(&mut self, max_bank_angle: f32) {
+        self.max_bank_angle = max_bank_angle;
+        self.sync_orientation();
     }
 
     #[func]
@@ -52,7 +136,7 @@ impl Boid3D {
 #[godot_api]
 impl INode3D for Boid3D {
     fn enter_tree(&mut self) {
-        let Some(mut flock) = self
+        let Some(flock) = self
             .to_gd()
             .get_parent()
             .and_then(|gd| gd.try_cast::<Flock3D>().ok())
@@ -61,49 +145,73 @@ impl INode3D for Boid3D {
             godot_error!("[Boid3D:{boid_id}] boids parent isn't a Flock3D, or has no parent");
             return;
         };
-        let mut flock = flock.bind_mut();
-        flock.register_boid(self.get_id());
-        self.flock_id = Some(flock.get_id());
-    }
-
-    fn ready(&mut self) {
-        if let Some(props) = self.properties.as_ref() {
-            self.props = props.bind().clone();
-        }
+        flock.bind().register_boid(
+            self.get_id(),
+            to_glam_vec(self.base().get_position()),
+            self.vel,
+            self.properties.clone(),
+        );
+        self.flock_id = Some(flock.instance_id());
+        self.sync_orientation();
     }
 
     fn exit_tree(&mut self) {
-        let mut flock: Gd<Flock3D> = Gd::from_instance_id(self.get_flock_id());
-        flock.bind_mut().unregister_boid(self.get_id());
+        let flock: Gd<Flock3D> = Gd::from_instance_id(self.get_flock_id());
+        let boid = flock.bind().unregister_boid(self.get_id());
+        if let Some(boid) = boid {
+            self.vel = boid.velocity;
+        }
     }
 }
 
-impl Boid for Boid3D {
-    #[inline(always)]
-    fn apply_force(&mut self, force: Vec3) {
-        self.vel += force;
-        self.vel = self.vel.clamp_length_max(self.props.max_speed);
-        let force_to_apply = Vector3::new(self.vel.x, self.vel.y, self.vel.z);
-        self.base_mut().translate(force_to_apply);
-    }
-
+impl Boid3D {
+    /// How this boid turns to face its velocity, if it does.
     #[inline(always)]
-    fn get_boid_position(&self) -> Vec3 {
-        to_glam_vec(self.base().get_position())
+    fn get_orientation(&self) -> Option<boids_core::Orientation> {
+        self.align_to_velocity.then(|| boids_core::Orientation {
+            max_turn_rate: max_turn_rate(self.max_turn_rate),
+            up: to_glam_vec(self.up_vector).normalize_or(Vec3::Y),
+            banking: self.banking,
+            max_bank: self.max_bank_angle.to_radians(),
+        })
     }
 
-    #[inline(always)]
-    fn get_boid_velocity(&self) -> Vec3 {
-        self.vel
+    /// Hands the orientation settings of this boid to the `Boids` singleton, if it's in a flock.
+    fn sync_orientation(&self) {
+        if let Some(flock_id) = self.flock_id {
+            get_singleton().bind_mut().set_boid_orientation_3d(
+                flock_id,
+                self.get_id(),
+                self.get_orientation(),
+            );
+        }
     }
+}
 
+impl Boid for Boid3D {
     #[inline(always)]
-    fn get_boid_properties(&self) -> &BoidProperties {
-        &self.props
+    fn get_boid_position(boid: &Gd<Self>) -> Vec3 {
+        to_glam_vec(boid.upcast_ref::<Node3D>().get_position())
     }
 
     #[inline(always)]
-    fn get_flock_id(&self) -> InstanceId {
-        self.get_flock_id()
+    fn set_boid_state(
+        boid: &mut Gd<Self>,
+        position: Vec3,
+        velocity: Vec3,
+        acceleration: Vec3,
+        orientation: Option<boids_core::Orientation>,
+        delta: f32,
+    ) {
+        let node = boid.upcast_mut::<Node3D>();
+        node.set_position(Vector3::new(position.x, position.y, position.z));
+        if let Some(orientation) = orientation {
+            let quat = node.get_quaternion();
+            let current = Quat::from_xyzw(quat.x, quat.y, quat.z, quat.w);
+            let rotation = orientation.rotation_3d(current, velocity, acceleration, delta);
+            node.set_quaternion(Quaternion::new(
+                rotation.x, rotation.y, rotation.z, rotation.w,
+            ));
+        }
     }
 }
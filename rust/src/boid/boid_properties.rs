@@ -1,21 +1,31 @@
 use godot::prelude::*;
+use rustc_hash::FxHashMap;
 
 #[derive(Default, Clone, Debug, GodotClass)]
 #[class(init, base=Resource)]
 /// Properties for a 2D/3D boid.
-/// 
+///
 /// Note that these properties are used as is and do not get scaled / translated between 2D / 3D units.
 /// So copying 2D properties to a 3D boid will *not* behave the same.
 pub struct BoidProperties {
     #[export]
-    #[init(val = 4.0)]
-    /// Max speed of this boid.
+    #[init(val = 240.0)]
+    /// Max speed of this boid, in units per second.
     pub max_speed: f32,
     #[export]
-    #[init(val = 1.0)]
-    /// Max force that will be applied to this boid at once.
+    #[init(val = 3600.0)]
+    /// Max force (acceleration) that will be applied to this boid, in units per second squared.
     pub max_force: f32,
     #[export]
+    /// Min speed of this boid, in units per second. Boids that are moving never go slower than this.
+    pub min_speed: f32,
+    #[export]
+    /// Drag that slows this boid down proportionally to its speed.
+    pub linear_drag: f32,
+    #[export]
+    /// Drag that slows this boid down proportionally to its speed squared.
+    pub quadratic_drag: f32,
+    #[export]
     #[init(val = 1.5)]
     /// How much to align with other boids.
     pub alignment: f32,
@@ -29,6 +39,90 @@ pub struct BoidProperties {
     pub seperation: f32,
     #[export]
     #[init(val = 0.8)]
-    /// How much to follow a flock target (if there is one).
+    /// How much to follow the targets, path or leader of the flock (if there are any).
     pub targeting: f32,
+    #[export]
+    #[init(val = 100.0)]
+    /// Distance from a target in `Arrive` mode at which this boid starts slowing down.
+    pub arrive_slowing_radius: f32,
+    #[export]
+    /// Distance from a target in `Arrive` mode at which this boid wants to stop completely.
+    pub arrive_stop_distance: f32,
+    #[export]
+    #[init(val = 2.0)]
+    /// How much to avoid obstacles.
+    pub avoidance: f32,
+    #[export]
+    #[init(val = 1.0)]
+    /// How much to go along with flow fields (`BoidFlowField2D` / `BoidFlowField3D`).
+    pub flow: f32,
+    #[export]
+    /// How much to wander around. Makes boids look alive without a target or neighbours.
+    pub wander_strength: f32,
+    #[export]
+    #[init(val = 120.0)]
+    /// Radius of the circle the wander point moves on. The circle is one second ahead of this boid, at its current velocity.
+    /// The larger this is compared to the speed of this boid, the sharper it turns while wandering.
+    pub wander_radius: f32,
+    #[export]
+    #[init(val = 240.0)]
+    /// How far the wander point moves randomly per second.
+    pub wander_jitter: f32,
+}
+
+impl From<&BoidProperties> for boids_core::BoidProperties {
+    fn from(props: &BoidProperties) -> Self {
+        Self {
+            max_speed: props.max_speed,
+            max_force: props.max_force,
+            min_speed: props.min_speed,
+            linear_drag: props.linear_drag,
+            quadratic_drag: props.quadratic_drag,
+            alignment: props.alignment,
+            cohesion: props.cohesion,
+            seperation: props.seperation,
+            targeting: props.targeting,
+            arrive_slowing_radius: props.arrive_slowing_radius,
+            arrive_stop_distance: props.arrive_stop_distance,
+            avoidance: props.avoidance,
+            flow: props.flow,
+            wander_strength: props.wander_strength,
+            wander_radius: props.wander_radius,
+            wander_jitter: props.wander_jitter,
+        }
+    }
+}
+
+/// Collects the properties of boids into a list of `boids_core::BoidProperties`.
+/// Boids that share a `BoidProperties` resource share one entry, so every resource is only read once.
+#[derive(Default)]
+pub struct BoidPropertiesInterner {
+    indices: FxHashMap<Option<InstanceId>, usize>,
+    properties: Vec<boids_core::BoidProperties>,
+}
+
+impl BoidPropertiesInterner {
+    /// Returns the index of the entry for `props`, adding one if it wasn't seen yet.
+    /// `None` uses the default properties of `boids_core::BoidProperties`, the same as a new resource.
+    #[inline(always)]
+    pub fn intern(&mut self, props: Option<&Gd<BoidProperties>>) -> usize {
+        *self
+            .indices
+            .entry(props.map(Gd::instance_id))
+            .or_insert_with(|| {
+                let core_props = match props {
+                    Some(props) => (&*props.bind()).into(),
+                    None => boids_core::BoidProperties::default(),
+                };
+                self.properties.push(core_props);
+                self.properties.len() - 1
+            })
+    }
+
+    /// Moves the collected properties into `properties`, leaving the interner empty to be reused.
+    pub fn drain_into(&mut self, properties: &mut Vec<boids_core::BoidProperties>) {
+        self.indices.clear();
+        properties.clear();
+        properties.append(&mut self.properties);
+    }
 }
@@ -1,8 +1,14 @@
+use std::sync::Arc;
+
 use glam::*;
-use godot::prelude::*;
+use godot::{
+    classes::{NavigationServer3D, Path3D, PhysicsRayQueryParameters3D},
+    prelude::*,
+};
 
 use crate::{
-    get_singleton, to_glam_vec, Boid, Boid3D, BoidProperties, FlockProperties, FxIndexMap,
+    get_singleton, to_glam_affine_3d, to_glam_vec, BoidProperties, BoidTarget3D, BoundsMode,
+    FlockProperties, Partitioning, PathMode, TargetMode,
 };
 
 use super::Flock;
@@ -14,30 +20,150 @@ use super::Flock;
 pub struct Flock3D {
     #[export]
     /// Properties of this flock.
-    /// Note: this cannot be changed in runtime, aside from removing and readding the node.
+    /// Changes to the resource, or setting a different one, apply from the next physics tick.
     properties: Option<Gd<FlockProperties>>,
-    props: FlockProperties,
     #[export]
     /// A target node for the flock to follow.
     target: Option<Gd<Node3D>>,
-    pub boids: FxIndexMap<InstanceId, Gd<Boid3D>>,
+    #[export]
+    /// How the flock follows `target`. Use `Arrive` to have the flock settle on it instead of circling around it.
+    target_mode: TargetMode,
+    #[export]
+    /// Additional targets for the flock to follow or flee from, each with their own weight and mode.
+    targets: Array<Option<Gd<BoidTarget3D>>>,
+    #[export]
+    /// How boids in this flock are partitioned to speed up finding neighbours.
+    /// `Tree` uses an octree, which can be better than `Grid` when boids are packed in a few dense clusters.
+    partitioning: Partitioning,
+    #[export]
+    /// How boids are kept inside of `bounds`.
+    bounds_mode: BoundsMode,
+    #[export]
+    #[init(val = Aabb::new(Vector3::new(-10.0, -10.0, -10.0), Vector3::new(20.0, 20.0, 20.0)))]
+    /// The box boids are kept inside of, relative to this node.
+    bounds: Aabb,
+    #[export]
+    #[init(val = 2.0)]
+    /// How close to the edges of `bounds` boids start steering back, with `BoundsMode.SteerBack`.
+    bounds_margin: f32,
+    #[export]
+    /// A path for the boids of this flock to follow along. Boids stay within `path_radius` of its curve.
+    path: Option<Gd<Path3D>>,
+    #[export]
+    /// What boids do at the ends of `path`.
+    path_mode: PathMode,
+    #[export]
+    #[init(val = 1.0)]
+    /// How far from `path` boids can stray before they steer back onto it.
+    path_radius: f32,
+    #[export]
+    #[init(val = 0.5)]
+    /// How far ahead (in seconds) boids predict their position to check if they're straying from `path`.
+    path_predict: f32,
+    #[export]
+    #[init(val = 1.0)]
+    /// How strong `path` is compared to the targets of this flock.
+    path_weight: f32,
+    #[export]
+    /// A node for the boids of this flock to follow behind, in a line. Boids ahead of it get out of its way.
+    /// Can be a `Boid3D` of this flock, which doesn't follow itself. Other nodes lead in the direction they face (-Z).
+    /// Boids follow the leader as much as they follow targets, see `BoidProperties.targeting`.
+    leader: Option<Gd<Node3D>>,
+    #[export]
+    #[init(val = 2.0)]
+    /// How far behind `leader` boids follow it. Boids less than this far ahead of it get out of its way.
+    leader_distance: f32,
+    #[export]
+    #[init(val = 1.0)]
+    /// How far from the path of `leader` boids that are ahead of it stay.
+    leader_clearance: f32,
+    #[export]
+    /// If enabled, the flock follows a path to `target` found by the `NavigationServer3D`, instead of going straight for it.
+    /// Paths are found from the center of the flock, on the navigation map of the world of this node.
+    navigation: bool,
+    #[export(flags_3d_navigation)]
+    #[init(val = 1)]
+    /// Navigation layers the path to `target` can go through.
+    navigation_layers: u32,
+    #[export]
+    #[init(val = 0.5)]
+    /// How often (in seconds) the path to `target` is found again.
+    navigation_interval: f32,
+    #[export]
+    #[init(val = 1.0)]
+    /// How close the center of the flock has to get to a point of the path before going on to the next one.
+    navigation_waypoint_radius: f32,
+    /// Points of the path to `target`, in global space.
+    navigation_path: PackedVector3Array,
+    /// Index of the point of `navigation_path` the flock is going to.
+    navigation_waypoint: usize,
+    /// Seconds until the path to `target` is found again.
+    navigation_timer: f32,
+    /// The baked points of the curve of `path` (by the ID of the curve), in the space of the path.
+    /// Only baked again when the curve changes.
+    path_cache: Option<(InstanceId, Arc<[Vec3]>)>,
+    #[export]
+    /// Whether boids should cast rays along their velocity to avoid physics bodies (eg. tilemaps or level geometry).
+    /// Rays are cast on the main thread, so this gets expensive with a lot of boids.
+    raycast_avoidance: bool,
+    #[export(flags_3d_physics)]
+    #[init(val = 1)]
+    /// Collision layers the avoidance rays collide with.
+    raycast_collision_mask: u32,
+    #[export]
+    #[init(val = 4.0)]
+    /// How far ahead boids look for physics bodies.
+    raycast_length: f32,
     base: Base<Node3D>,
 }
 
 impl Flock3D {
-    pub fn register_boid(&mut self, boid_id: InstanceId) {
-        let boid: Gd<Boid3D> = Gd::from_instance_id(boid_id);
-        self.boids.insert(boid_id, boid.clone());
-        get_singleton().bind_mut().register_boid_3d(boid_id, boid);
+    pub fn register_boid(
+        &self,
+        boid_id: InstanceId,
+        position: Vec3,
+        velocity: Vec3,
+        properties: Option<Gd<BoidProperties>>,
+    ) {
         let flock_id = self.get_id();
+        get_singleton().bind_mut().register_boid_3d(
+            flock_id,
+            boid_id,
+            boids_core::Boid::new(position, velocity, 0),
+            properties,
+        );
         godot_print!("[Flock3D:{flock_id}] boid {boid_id} registered");
     }
 
-    pub fn unregister_boid(&mut self, boid_id: InstanceId) {
-        self.boids.shift_remove(&boid_id);
-        get_singleton().bind_mut().unregister_boid_3d(boid_id);
+    /// The point of the navigation path the flock is going to, unless it's the last one (`target` itself).
+    #[inline(always)]
+    fn navigation_waypoint(&self) -> Option<Vector3> {
+        if self.navigation_waypoint + 1 < self.navigation_path.len() {
+            self.navigation_path.get(self.navigation_waypoint)
+        } else {
+            None
+        }
+    }
+
+    /// Finds a path from `from` to `to` on the navigation map of the world of this node, in global space.
+    fn find_navigation_path(&self, from: Vector3, to: Vector3) -> PackedVector3Array {
+        let Some(world) = self.base().get_world_3d() else {
+            return PackedVector3Array::new();
+        };
+        NavigationServer3D::singleton()
+            .map_get_path_ex(world.get_navigation_map(), from, to, true)
+            .navigation_layers(self.navigation_layers)
+            .done()
+    }
+
+    /// Removes a boid from this flock, returning its last simulated state.
+    pub fn unregister_boid(&self, boid_id: InstanceId) -> Option<boids_core::Boid> {
         let flock_id = self.get_id();
+        let boid = get_singleton()
+            .bind_mut()
+            .unregister_boid_3d(flock_id, boid_id);
         godot_print!("[Flock3D:{flock_id}] boid {boid_id} unregistered");
+        boid
     }
 }
 
@@ -47,12 +173,6 @@ impl INode3D for Flock3D {
         get_singleton().bind_mut().register_flock_3d(self.get_id())
     }
 
-    fn ready(&mut self) {
-        if let Some(props) = self.properties.as_ref() {
-            self.props = props.bind().clone();
-        }
-    }
-
     fn exit_tree(&mut self) {
         get_singleton()
             .bind_mut()
@@ -68,42 +188,192 @@ impl Flock3D {
     pub fn get_id(&self) -> InstanceId {
         self.base().instance_id()
     }
+
+    #[func]
+    /// Forget the baked points of the curve of `path`, so they're baked again on the next physics tick.
+    /// Called automatically when the curve changes.
+    fn invalidate_path_cache(&mut self) {
+        self.path_cache = None;
+    }
 }
 
 impl Flock for Flock3D {
+    const DIMENSIONS: usize = 3;
+
     #[inline(always)]
-    fn get_flock_properties(&self) -> &FlockProperties {
-        &self.props
+    fn get_flock_properties(&self) -> boids_core::FlockProperties {
+        match self.properties.as_ref() {
+            Some(props) => (&*props.bind()).into(),
+            None => boids_core::FlockProperties::default(),
+        }
     }
 
     #[inline(always)]
-    fn get_target_position(&self) -> Option<Vec3> {
-        self.target.as_ref().map(|t| to_glam_vec(t.get_position()))
+    fn get_targets(&self) -> Vec<boids_core::Target> {
+        let to_flock = self.base().get_global_transform().affine_inverse();
+        let target = self
+            .target
+            .as_ref()
+            .map(|t| match self.navigation_waypoint() {
+                Some(point) => boids_core::Target::new(
+                    to_glam_vec(to_flock * point),
+                    boids_core::TargetMode::Seek,
+                ),
+                None => boids_core::Target::new(
+                    to_glam_vec(to_flock * t.get_global_position()),
+                    self.target_mode.into(),
+                ),
+            });
+        target
+            .into_iter()
+            .chain(
+                self.targets
+                    .iter_shared()
+                    .flatten()
+                    .map(|t| t.bind().to_core_target(to_flock)),
+            )
+            .collect()
     }
 
     #[inline(always)]
-    fn get_boids_posvel(&self) -> Vec<(Vec3, Vec3)> {
-        let boid_count = self.boids.len();
-        let mut result = Vec::with_capacity(boid_count);
-        result.extend(self.boids.values().map(|b| {
-            let b = b.bind();
-            (b.get_boid_position(), b.get_boid_velocity())
-        }));
-        result
+    fn get_partitioning(&self) -> Partitioning {
+        self.partitioning
     }
 
     #[inline(always)]
-    fn get_boids(&self) -> impl Iterator<Item = (&InstanceId, (Vec3, Vec3, BoidProperties))> {
-        self.boids.iter().map(|(id, boid)| {
-            let boid = boid.bind();
-            (
-                id,
-                (
-                    boid.get_boid_position(),
-                    boid.get_boid_velocity(),
-                    boid.get_boid_properties().clone(),
-                ),
-            )
-        })
+    fn get_global_affine(&self) -> Affine3A {
+        to_glam_affine_3d(self.base().get_global_transform())
+    }
+
+    #[inline(always)]
+    fn get_bounds(&self) -> Option<boids_core::Bounds> {
+        let mode = self.bounds_mode.to_core()?;
+        let (min, max) = (self.bounds.position, self.bounds.end());
+        Some(
+            boids_core::Bounds::new_3d(to_glam_vec(min), to_glam_vec(max), mode)
+                .with_margin(self.bounds_margin),
+        )
+    }
+
+    fn get_path(&mut self) -> Option<boids_core::Path> {
+        let path = self.path.clone()?;
+        let mut curve = path.get_curve()?;
+        let curve_id = curve.instance_id();
+        let points = match &self.path_cache {
+            Some((id, points)) if *id == curve_id => points.clone(),
+            _ => {
+                let invalidate = self.base().callable("invalidate_path_cache");
+                if !curve.is_connected("changed", &invalidate) {
+                    curve.connect("changed", &invalidate);
+                }
+                let points: Arc<[Vec3]> = curve
+                    .get_baked_points()
+                    .as_slice()
+                    .iter()
+                    .map(|point| to_glam_vec(*point))
+                    .collect();
+                self.path_cache = Some((curve_id, points.clone()));
+                points
+            }
+        };
+        let from_path = to_glam_affine_3d(
+            self.base().get_global_transform().affine_inverse() * path.get_global_transform(),
+        );
+        Some(
+            boids_core::Path::new(points, self.path_mode.into())
+                .with_radius(self.path_radius)
+                .with_predict(self.path_predict)
+                .with_weight(self.path_weight)
+                .with_transform(from_path),
+        )
+    }
+
+    #[inline(always)]
+    fn get_leader(&self) -> Option<(InstanceId, boids_core::Leader)> {
+        let leader = self.leader.as_ref()?;
+        let to_flock = self.base().get_global_transform().affine_inverse();
+        let Transform3D { basis, origin } = to_flock * leader.get_global_transform();
+        let core_leader = boids_core::Leader::new(to_glam_vec(origin), -to_glam_vec(basis.col_c()))
+            .with_distance(self.leader_distance)
+            .with_clearance(self.leader_clearance);
+        Some((leader.instance_id(), core_leader))
+    }
+
+    fn update_navigation(&mut self, centroid: Vec3, delta: f32) {
+        let target = match (&self.target, self.navigation) {
+            (Some(target), true) => target.get_global_position(),
+            _ => {
+                self.navigation_path.clear();
+                self.navigation_timer = 0.0;
+                return;
+            }
+        };
+        let centroid = Vector3::new(centroid.x, centroid.y, centroid.z);
+        self.navigation_timer -= delta;
+        if self.navigation_timer <= 0.0 {
+            self.navigation_timer = self.navigation_interval;
+            self.navigation_path = self.find_navigation_path(centroid, target);
+            // the first point is where the flock already is
+            self.navigation_waypoint = 1;
+        }
+        let last = self.navigation_path.len().saturating_sub(1);
+        while self.navigation_waypoint < last
+            && self
+                .navigation_path
+                .get(self.navigation_waypoint)
+                .is_some_and(|point| point.distance_to(centroid) < self.navigation_waypoint_radius)
+        {
+            self.navigation_waypoint += 1;
+        }
+    }
+
+    fn cast_avoidance_rays(&self, boids: &mut boids_core::BoidStorage) {
+        if !self.raycast_avoidance || boids.is_empty() {
+            return;
+        }
+        let Some(mut space) = self
+            .base()
+            .get_world_3d()
+            .and_then(|mut world| world.get_direct_space_state())
+        else {
+            return;
+        };
+        let Some(mut query) = PhysicsRayQueryParameters3D::create(Vector3::ZERO, Vector3::ZERO)
+        else {
+            return;
+        };
+        query.set_collision_mask(self.raycast_collision_mask);
+
+        // boids are positioned relative to the flock, but the physics world is global
+        let transform = self.base().get_global_transform();
+        let inverse = transform.affine_inverse();
+        let boids = boids
+            .positions
+            .iter()
+            .zip(&boids.velocities)
+            .zip(&mut boids.external_repulsion);
+        for ((position, velocity), external_repulsion) in boids {
+            let dir = velocity.normalize_or_zero();
+            if dir == Vec3::ZERO {
+                continue;
+            }
+            let from = *position;
+            let to = from + dir * self.raycast_length;
+            query.set_from(transform * Vector3::new(from.x, from.y, from.z));
+            query.set_to(transform * Vector3::new(to.x, to.y, to.z));
+            let hit = space.intersect_ray(&query);
+            let (Some(hit_pos), Some(hit_normal)) = (hit.get("position"), hit.get("normal")) else {
+                continue;
+            };
+            let hit_pos = to_glam_vec(inverse * hit_pos.to::<Vector3>());
+            let normal =
+                to_glam_vec(inverse.basis * hit_normal.to::<Vector3>()).normalize_or_zero();
+
+            // steer away from the surface, while sliding along it
+            let urgency = 1.0 - from.distance(hit_pos) / self.raycast_length;
+            let slide = dir - normal * dir.dot(normal);
+            let free_dir = (normal + slide).normalize_or_zero();
+            *external_repulsion += free_dir * urgency;
+        }
     }
 }
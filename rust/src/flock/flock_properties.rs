@@ -3,7 +3,7 @@ use godot::prelude::*;
 #[derive(Default, Clone, Debug, GodotClass)]
 #[class(tool, init, base=Resource)]
 /// Properties for a 2D/3D flock.
-/// 
+///
 /// Note that these properties are used as is and do not get scaled / translated between 2D / 3D units.
 /// So copying 2D properties to a 3D flock will *not* behave the same.
 pub struct FlockProperties {
@@ -19,4 +19,30 @@ pub struct FlockProperties {
     #[init(val = 2500.0)]
     /// Distance (squared) to apply cohesion force between boids in a flock.
     pub goal_cohesion: f32,
+    #[export(range = (0.0, 360.0, degrees))]
+    #[init(val = 360.0)]
+    /// Field of view in which neighbours are considered for seperation.
+    /// Neighbours behind the boid, outside of this view cone, are ignored.
+    pub fov_seperation: f32,
+    #[export(range = (0.0, 360.0, degrees))]
+    #[init(val = 360.0)]
+    /// Field of view in which neighbours are considered for alignment.
+    pub fov_alignment: f32,
+    #[export(range = (0.0, 360.0, degrees))]
+    #[init(val = 360.0)]
+    /// Field of view in which neighbours are considered for cohesion.
+    pub fov_cohesion: f32,
+}
+
+impl From<&FlockProperties> for boids_core::FlockProperties {
+    fn from(props: &FlockProperties) -> Self {
+        Self {
+            goal_seperation: props.goal_seperation,
+            goal_alignment: props.goal_alignment,
+            goal_cohesion: props.goal_cohesion,
+            fov_seperation: props.fov_seperation,
+            fov_alignment: props.fov_alignment,
+            fov_cohesion: props.fov_cohesion,
+        }
+    }
 }
@@ -1,8 +1,6 @@
 use glam::*;
 use godot::prelude::*;
 
-use crate::BoidProperties;
-
 pub mod flock_2d;
 pub mod flock_3d;
 pub mod flock_properties;
@@ -11,9 +9,137 @@ pub use flock_2d::*;
 pub use flock_3d::*;
 pub use flock_properties::*;
 
+#[derive(GodotConvert, Var, Export, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+/// How a flock partitions its boids to speed up neighbour lookups.
+pub enum Partitioning {
+    /// Check every boid against every other boid in the flock.
+    BruteForce,
+    #[default]
+    /// Use a uniform grid, with cells sized from the largest radius in the flock properties.
+    /// Works best when boids are spread evenly.
+    Grid,
+    /// Use a quadtree for 2D flocks, or an octree for 3D flocks.
+    /// Works best when the density of boids is uneven.
+    Tree,
+}
+
+impl From<Partitioning> for boids_core::Partitioning {
+    fn from(partitioning: Partitioning) -> Self {
+        match partitioning {
+            Partitioning::BruteForce => Self::BruteForce,
+            Partitioning::Grid => Self::Grid,
+            Partitioning::Tree => Self::Tree,
+        }
+    }
+}
+
+#[derive(GodotConvert, Var, Export, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+/// How a flock keeps its boids inside of its bounds.
+pub enum BoundsMode {
+    #[default]
+    /// Boids aren't kept inside of the bounds.
+    Disabled,
+    /// Boids leaving the bounds come back in on the opposite side, and flock with boids across the edges.
+    Wrap,
+    /// Boids bounce off the edges of the bounds.
+    Bounce,
+    /// Boids steer back inside when they get closer than the bounds margin to the edges.
+    SteerBack,
+}
+
+impl BoundsMode {
+    /// Converts to the core bounds mode, or `None` if bounds are disabled.
+    pub fn to_core(self) -> Option<boids_core::BoundsMode> {
+        match self {
+            Self::Disabled => None,
+            Self::Wrap => Some(boids_core::BoundsMode::Wrap),
+            Self::Bounce => Some(boids_core::BoundsMode::Bounce),
+            Self::SteerBack => Some(boids_core::BoundsMode::SteerBack),
+        }
+    }
+}
+
+#[derive(GodotConvert, Var, Export, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+/// How the boids of a flock react to the boids of another flock, see `Boids.set_flock_relation`.
+pub enum RelationMode {
+    #[default]
+    /// Don't react to the other flock.
+    Ignore,
+    /// Keep some distance from nearby boids of the other flock.
+    Separate,
+    /// Flee from the closest nearby boid of the other flock.
+    Flee,
+    /// Chase the closest nearby boid of the other flock.
+    Chase,
+}
+
+impl From<RelationMode> for boids_core::RelationMode {
+    fn from(mode: RelationMode) -> Self {
+        match mode {
+            RelationMode::Ignore => Self::Ignore,
+            RelationMode::Separate => Self::Separate,
+            RelationMode::Flee => Self::Flee,
+            RelationMode::Chase => Self::Chase,
+        }
+    }
+}
+
+#[derive(GodotConvert, Var, Export, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+/// What boids do at the ends of a flock path.
+pub enum PathMode {
+    #[default]
+    /// The path is closed, boids go from its end back to its start.
+    Loop,
+    /// Boids turn around at the ends of the path.
+    PingPong,
+}
+
+impl From<PathMode> for boids_core::PathMode {
+    fn from(mode: PathMode) -> Self {
+        match mode {
+            PathMode::Loop => Self::Loop,
+            PathMode::PingPong => Self::PingPong,
+        }
+    }
+}
+
 pub trait Flock {
-    fn get_flock_properties(&self) -> &FlockProperties;
-    fn get_target_position(&self) -> Option<Vec3>;
-    fn get_boids(&self) -> impl Iterator<Item = (&InstanceId, (Vec3, Vec3, BoidProperties))>;
-    fn get_boids_posvel(&self) -> Vec<(Vec3, Vec3)>;
+    /// Whether this is a 2D or a 3D flock.
+    const DIMENSIONS: usize;
+
+    /// The properties of this flock, read from its properties resource.
+    fn get_flock_properties(&self) -> boids_core::FlockProperties;
+    fn get_targets(&self) -> Vec<boids_core::Target>;
+    fn get_partitioning(&self) -> Partitioning;
+    /// The bounds boids of this flock are kept inside of, in the space of the flock.
+    fn get_bounds(&self) -> Option<boids_core::Bounds>;
+    /// Transforms positions from the space of this flock into global space.
+    fn get_global_affine(&self) -> Affine3A;
+    /// The path of this flock, if any, with its transform into the space of the flock.
+    fn get_path(&mut self) -> Option<boids_core::Path>;
+    /// The node the boids of this flock follow behind, if any, with its ID in case it's a boid of this flock.
+    fn get_leader(&self) -> Option<(InstanceId, boids_core::Leader)>;
+    /// Finds the path to the target of this flock again when it's time to, and goes on along it
+    /// as `centroid` (the center of the boids, in global space) gets close to its points.
+    /// Does nothing if navigation isn't enabled for this flock.
+    #[inline(always)]
+    fn update_navigation(&mut self, _centroid: Vec3, _delta: f32) {}
+    /// Casts rays through the physics world along the velocity of `boids`, setting their external repulsion on hits.
+    /// Does nothing if raycast avoidance isn't enabled for this flock.
+    fn cast_avoidance_rays(&self, boids: &mut boids_core::BoidStorage);
+
+    /// Copies the settings of this flock into `flock`, leaving its boids as they are.
+    #[inline(always)]
+    fn update_core_flock(&mut self, flock: &mut boids_core::Flock) {
+        flock.properties = self.get_flock_properties();
+        flock.partitioning = self.get_partitioning().into();
+        flock.targets = self.get_targets();
+        flock.bounds = self.get_bounds();
+        flock.path = self.get_path();
+        flock.environment_space = boids_core::EnvironmentSpace::new(self.get_global_affine());
+    }
 }
@@ -0,0 +1,203 @@
+use std::f32::consts::PI;
+
+use glam::*;
+use godot::{
+    classes::{Image, Noise},
+    prelude::*,
+};
+
+use crate::{get_singleton, to_glam_affine_2d};
+
+use super::FlowField;
+
+#[derive(GodotClass)]
+#[class(init, base=Node2D)]
+/// A 2D grid of vectors (eg. wind or a river current) that boids inside of it align with.
+/// The grid is centered on this node, and follows its rotation and scale.
+/// How much boids go along with it depends on their `flow` property.
+pub struct BoidFlowField2D {
+    #[export]
+    #[var(get, set = set_size)]
+    #[init(val = Vector2i::new(16, 16))]
+    /// How many cells the grid has along each axis.
+    size: Vector2i,
+    #[export]
+    #[var(get, set = set_cell_size)]
+    #[init(val = 32.0)]
+    /// Width of every cell of the grid.
+    cell_size: f32,
+    #[export]
+    #[init(val = 1.0)]
+    /// How strong this field is compared to other fields.
+    weight: f32,
+    #[export]
+    #[var(get, set = set_vectors)]
+    /// The vector of every cell, row by row. Its length is how strongly boids align with it, up to 1.
+    /// Missing vectors are zero.
+    vectors: PackedVector2Array,
+    /// The core field in the space of this node, until the grid changes.
+    cache: Option<boids_core::FlowField>,
+    base: Base<Node2D>,
+}
+
+#[godot_api]
+impl INode2D for BoidFlowField2D {
+    fn enter_tree(&mut self) {
+        get_singleton()
+            .bind_mut()
+            .register_flow_field_2d(self.get_id())
+    }
+
+    fn exit_tree(&mut self) {
+        get_singleton()
+            .bind_mut()
+            .unregister_flow_field_2d(self.get_id())
+    }
+}
+
+#[godot_api]
+impl BoidFlowField2D {
+    #[func]
+    #[inline(always)]
+    /// Retrieve the ID of this flow field.
+    pub fn get_id(&self) -> InstanceId {
+        self.base().instance_id()
+    }
+
+    #[func]
+    /// Set how many cells the grid has along each axis.
+    fn set_size(&mut self, size: Vector2i) {
+        self.size = size;
+        self.cache = None;
+    }
+
+    #[func]
+    /// Set the width of every cell of the grid.
+    fn set_cell_size(&mut self, cell_size: f32) {
+        self.cell_size = cell_size;
+        self.cache = None;
+    }
+
+    #[func]
+    /// Set the vector of every cell.
+    fn set_vectors(&mut self, vectors: PackedVector2Array) {
+        self.vectors = vectors;
+        self.cache = None;
+    }
+
+    #[func]
+    /// Get the vector of the cell at `cell`, or zero if it's outside of the grid.
+    fn get_cell_vector(&self, cell: Vector2i) -> Vector2 {
+        self.cell_index(cell)
+            .and_then(|index| self.vectors.get(index))
+            .unwrap_or(Vector2::ZERO)
+    }
+
+    #[func]
+    /// Set the vector of the cell at `cell`. Does nothing if it's outside of the grid.
+    fn set_cell_vector(&mut self, cell: Vector2i, vector: Vector2) {
+        if let Some(index) = self.cell_index(cell) {
+            self.fit_vectors();
+            self.vectors[index] = vector;
+        }
+    }
+
+    #[func]
+    /// Set the vectors of all cells from the red and green channels of `image`, stretched over the grid.
+    /// Channels go from 0 to 1, and map to vectors from -1 to 1 (like a flow map). The image can't be compressed.
+    fn bake_from_image(&mut self, image: Gd<Image>) {
+        let (width, height) = (image.get_width(), image.get_height());
+        if width == 0 || height == 0 || image.is_compressed() {
+            godot_error!("[BoidFlowField2D] can't bake from an empty or compressed image");
+            return;
+        }
+        self.bake(|cell, _| {
+            let x = (cell.x * width as f32) as i32;
+            let y = (cell.y * height as f32) as i32;
+            let color = image.get_pixel(x.min(width - 1), y.min(height - 1));
+            Vector2::new(color.r * 2.0 - 1.0, color.g * 2.0 - 1.0)
+        });
+    }
+
+    #[func]
+    /// Set the vectors of all cells to unit vectors, turned by `noise` at the center of each cell (in the space of this node).
+    fn bake_from_noise(&mut self, noise: Gd<Noise>) {
+        self.bake(|_, pos| {
+            let angle = noise.get_noise_2d(pos.x, pos.y) * PI;
+            Vector2::from_angle(angle)
+        });
+    }
+}
+
+impl BoidFlowField2D {
+    #[inline(always)]
+    fn cell_count(&self) -> usize {
+        (self.size.x.max(0) * self.size.y.max(0)) as usize
+    }
+
+    #[inline(always)]
+    fn cell_index(&self, cell: Vector2i) -> Option<usize> {
+        let inside = cell.x >= 0 && cell.y >= 0 && cell.x < self.size.x && cell.y < self.size.y;
+        inside.then(|| (cell.x + cell.y * self.size.x) as usize)
+    }
+
+    /// Resizes `vectors` to have one vector for every cell, before they're changed.
+    fn fit_vectors(&mut self) {
+        self.vectors.resize(self.cell_count());
+        self.cache = None;
+    }
+
+    /// Sets every vector to `f(cell, pos)`, with `cell` the center of the cell relative to the size of the grid (from 0 to 1),
+    /// and `pos` the center of the cell in the space of this node.
+    fn bake(&mut self, mut f: impl FnMut(Vector2, Vector2) -> Vector2) {
+        self.fit_vectors();
+        let size = self.size;
+        let half = Vector2::new(size.x as f32, size.y as f32) * self.cell_size * 0.5;
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let center = Vector2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let cell = center / Vector2::new(size.x as f32, size.y as f32);
+                let pos = center * self.cell_size - half;
+                self.vectors[(x + y * size.x) as usize] = f(cell, pos);
+            }
+        }
+    }
+
+    /// Builds the core field of this node, with `to_grid` mapping from the space of this node.
+    fn local_flow_field(&self) -> boids_core::FlowField {
+        let size = uvec3(self.size.x.max(0) as u32, self.size.y.max(0) as u32, 1);
+        let mut vectors: Vec<Vec3> = self
+            .vectors
+            .as_slice()
+            .iter()
+            .map(|v| vec3(v.x, v.y, 0.0))
+            .collect();
+        vectors.resize(self.cell_count(), Vec3::ZERO);
+
+        // the grid is centered on this node
+        let half = vec3(size.x as f32, size.y as f32, 0.0) * self.cell_size * 0.5;
+        let to_grid = Affine3A::from_scale(vec3(1.0 / self.cell_size, 1.0 / self.cell_size, 1.0))
+            * Affine3A::from_translation(half);
+        boids_core::FlowField::new(size, vectors, to_grid)
+    }
+}
+
+impl FlowField for BoidFlowField2D {
+    #[inline(always)]
+    fn to_core_flow_field(&mut self) -> boids_core::FlowField {
+        let to_local = to_glam_affine_2d(self.base().get_global_transform()).inverse();
+        let local = match &self.cache {
+            Some(field) => field.clone(),
+            None => {
+                let field = self.local_flow_field();
+                self.cache = Some(field.clone());
+                field
+            }
+        };
+        boids_core::FlowField {
+            to_grid: local.to_grid * to_local,
+            ..local
+        }
+        .with_weight(self.weight)
+    }
+}
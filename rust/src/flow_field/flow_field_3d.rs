@@ -0,0 +1,218 @@
+use glam::*;
+use godot::{
+    classes::{Image, Noise},
+    prelude::*,
+};
+
+use crate::{get_singleton, to_glam_affine_3d, to_glam_vec};
+
+use super::FlowField;
+
+#[derive(GodotClass)]
+#[class(init, base=Node3D)]
+/// A 3D grid of vectors (eg. wind or a river current) that boids inside of it align with.
+/// The grid is centered on this node, and follows its rotation and scale.
+/// How much boids go along with it depends on their `flow` property.
+pub struct BoidFlowField3D {
+    #[export]
+    #[var(get, set = set_size)]
+    #[init(val = Vector3i::new(8, 8, 8))]
+    /// How many cells the grid has along each axis.
+    size: Vector3i,
+    #[export]
+    #[var(get, set = set_cell_size)]
+    #[init(val = 2.0)]
+    /// Width of every cell of the grid.
+    cell_size: f32,
+    #[export]
+    #[init(val = 1.0)]
+    /// How strong this field is compared to other fields.
+    weight: f32,
+    #[export]
+    #[var(get, set = set_vectors)]
+    /// The vector of every cell, row by row and layer by layer (X first, then Y, then Z).
+    /// Its length is how strongly boids align with it, up to 1. Missing vectors are zero.
+    vectors: PackedVector3Array,
+    /// The core field in the space of this node, until the grid changes.
+    cache: Option<boids_core::FlowField>,
+    base: Base<Node3D>,
+}
+
+#[godot_api]
+impl INode3D for BoidFlowField3D {
+    fn enter_tree(&mut self) {
+        get_singleton()
+            .bind_mut()
+            .register_flow_field_3d(self.get_id())
+    }
+
+    fn exit_tree(&mut self) {
+        get_singleton()
+            .bind_mut()
+            .unregister_flow_field_3d(self.get_id())
+    }
+}
+
+#[godot_api]
+impl BoidFlowField3D {
+    #[func]
+    #[inline(always)]
+    /// Retrieve the ID of this flow field.
+    pub fn get_id(&self) -> InstanceId {
+        self.base().instance_id()
+    }
+
+    #[func]
+    /// Set how many cells the grid has along each axis.
+    fn set_size(&mut self, size: Vector3i) {
+        self.size = size;
+        self.cache = None;
+    }
+
+    #[func]
+    /// Set the width of every cell of the grid.
+    fn set_cell_size(&mut self, cell_size: f32) {
+        self.cell_size = cell_size;
+        self.cache = None;
+    }
+
+    #[func]
+    /// Set the vector of every cell.
+    fn set_vectors(&mut self, vectors: PackedVector3Array) {
+        self.vectors = vectors;
+        self.cache = None;
+    }
+
+    #[func]
+    /// Get the vector of the cell at `cell`, or zero if it's outside of the grid.
+    fn get_cell_vector(&self, cell: Vector3i) -> Vector3 {
+        self.cell_index(cell)
+            .and_then(|index| self.vectors.get(index))
+            .unwrap_or(Vector3::ZERO)
+    }
+
+    #[func]
+    /// Set the vector of the cell at `cell`. Does nothing if it's outside of the grid.
+    fn set_cell_vector(&mut self, cell: Vector3i, vector: Vector3) {
+        if let Some(index) = self.cell_index(cell) {
+            self.fit_vectors();
+            self.vectors[index] = vector;
+        }
+    }
+
+    #[func]
+    /// Set the vectors of every layer of cells from the red and green channels of `image`, stretched over the X and Z axes of the grid.
+    /// Channels go from 0 to 1, and map to X and Z from -1 to 1 (like a flow map), useful for currents along the ground.
+    /// The image can't be compressed.
+    fn bake_from_image(&mut self, image: Gd<Image>) {
+        let (width, height) = (image.get_width(), image.get_height());
+        if width == 0 || height == 0 || image.is_compressed() {
+            godot_error!("[BoidFlowField3D] can't bake from an empty or compressed image");
+            return;
+        }
+        self.bake(|cell, _| {
+            let x = (cell.x * width as f32) as i32;
+            let y = (cell.z * height as f32) as i32;
+            let color = image.get_pixel(x.min(width - 1), y.min(height - 1));
+            Vector3::new(color.r * 2.0 - 1.0, 0.0, color.g * 2.0 - 1.0)
+        });
+    }
+
+    #[func]
+    /// Set the vectors of all cells from `noise` at the center of each cell (in the space of this node).
+    /// Each axis is sampled from a different part of the noise.
+    fn bake_from_noise(&mut self, noise: Gd<Noise>) {
+        // far enough apart that the axes don't look alike
+        const OFFSET: f32 = 1000.0;
+        self.bake(|_, pos| {
+            let axis = |offset: f32| noise.get_noise_3d(pos.x + offset, pos.y, pos.z);
+            Vector3::new(axis(0.0), axis(OFFSET), axis(OFFSET * 2.0))
+        });
+    }
+}
+
+impl BoidFlowField3D {
+    #[inline(always)]
+    fn cell_count(&self) -> usize {
+        (self.size.x.max(0) * self.size.y.max(0) * self.size.z.max(0)) as usize
+    }
+
+    #[inline(always)]
+    fn cell_index(&self, cell: Vector3i) -> Option<usize> {
+        let size = self.size;
+        let inside = cell.x >= 0
+            && cell.y >= 0
+            && cell.z >= 0
+            && cell.x < size.x
+            && cell.y < size.y
+            && cell.z < size.z;
+        inside.then(|| (cell.x + size.x * (cell.y + size.y * cell.z)) as usize)
+    }
+
+    /// Resizes `vectors` to have one vector for every cell, before they're changed.
+    fn fit_vectors(&mut self) {
+        self.vectors.resize(self.cell_count());
+        self.cache = None;
+    }
+
+    /// Sets every vector to `f(cell, pos)`, with `cell` the center of the cell relative to the size of the grid (from 0 to 1),
+    /// and `pos` the center of the cell in the space of this node.
+    fn bake(&mut self, mut f: impl FnMut(Vector3, Vector3) -> Vector3) {
+        self.fit_vectors();
+        let size = self.size;
+        let grid_size = Vector3::new(size.x as f32, size.y as f32, size.z as f32);
+        let half = grid_size * self.cell_size * 0.5;
+        for z in 0..size.z {
+            for y in 0..size.y {
+                for x in 0..size.x {
+                    let center = Vector3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5);
+                    let pos = center * self.cell_size - half;
+                    self.vectors[(x + size.x * (y + size.y * z)) as usize] =
+                        f(center / grid_size, pos);
+                }
+            }
+        }
+    }
+
+    /// Builds the core field of this node, with `to_grid` mapping from the space of this node.
+    fn local_flow_field(&self) -> boids_core::FlowField {
+        let size = uvec3(
+            self.size.x.max(0) as u32,
+            self.size.y.max(0) as u32,
+            self.size.z.max(0) as u32,
+        );
+        let mut vectors: Vec<Vec3> = self
+            .vectors
+            .as_slice()
+            .iter()
+            .map(|v| to_glam_vec(*v))
+            .collect();
+        vectors.resize(self.cell_count(), Vec3::ZERO);
+
+        // the grid is centered on this node
+        let half = size.as_vec3() * self.cell_size * 0.5;
+        let to_grid = Affine3A::from_scale(Vec3::splat(1.0 / self.cell_size))
+            * Affine3A::from_translation(half);
+        boids_core::FlowField::new(size, vectors, to_grid)
+    }
+}
+
+impl FlowField for BoidFlowField3D {
+    #[inline(always)]
+    fn to_core_flow_field(&mut self) -> boids_core::FlowField {
+        let to_local = to_glam_affine_3d(self.base().get_global_transform()).inverse();
+        let local = match &self.cache {
+            Some(field) => field.clone(),
+            None => {
+                let field = self.local_flow_field();
+                self.cache = Some(field.clone());
+                field
+            }
+        };
+        boids_core::FlowField {
+            to_grid: local.to_grid * to_local,
+            ..local
+        }
+        .with_weight(self.weight)
+    }
+}
@@ -0,0 +1,11 @@
+pub mod flow_field_2d;
+pub mod flow_field_3d;
+
+pub use flow_field_2d::*;
+pub use flow_field_3d::*;
+
+pub trait FlowField {
+    /// Converts this node into a core flow field in global space.
+    /// The grid is cached, and only rebuilt when its vectors, size or cell size change.
+    fn to_core_flow_field(&mut self) -> boids_core::FlowField;
+}
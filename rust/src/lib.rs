@@ -1,19 +1,25 @@
-use std::sync::Arc;
+// the godot-rust macros generate code that trips this lint on newer clippy versions
+#![allow(clippy::result_large_err)]
 
 use glam::*;
-use godot::{
-    classes::Engine,
-    obj::{bounds::DeclUser, Bounds},
-    prelude::*,
-};
+use godot::{classes::Engine, global::randi, prelude::*};
 use indexmap::IndexMap;
-use rayon::prelude::*;
 
 mod boid;
 mod flock;
+mod flow_field;
+mod obstacle;
+mod swarm;
+mod target;
+mod world;
 
 pub use boid::{Boid, *};
 pub use flock::{Flock, *};
+pub use flow_field::{FlowField, *};
+pub use obstacle::{Obstacle, *};
+pub use swarm::*;
+pub use target::*;
+pub use world::*;
 
 use rustc_hash::FxBuildHasher;
 
@@ -33,12 +39,9 @@ struct BoidsExtension;
 #[gdextension]
 unsafe impl ExtensionLibrary for BoidsExtension {
     fn on_level_init(level: InitLevel) {
-        match level {
-            InitLevel::Scene => {
-                let singleton = Boids::new_alloc();
-                Engine::singleton().register_singleton(SINGLETON_NAME, &singleton);
-            }
-            _ => (),
+        if level == InitLevel::Scene {
+            let singleton = Boids::new_alloc();
+            Engine::singleton().register_singleton(SINGLETON_NAME, &singleton);
         }
     }
 
@@ -79,6 +82,13 @@ pub struct BoidsProcess {
     #[init(val = 1)]
     /// Process boids per N physics ticks.
     process_per_tick: i64,
+    #[export]
+    /// Whether to seed all randomness with `seed`, so that the same scene plays out the same way every run.
+    /// Otherwise a random seed is picked on ready.
+    deterministic: bool,
+    #[export]
+    /// The seed used when `deterministic` is enabled.
+    seed: i64,
     boids: Option<Gd<Boids>>,
     engine: Option<Gd<Engine>>,
 }
@@ -101,18 +111,30 @@ impl INode for BoidsProcess {
     fn ready(&mut self) {
         self.boids = Some(get_singleton());
         self.engine = Some(Engine::singleton());
+        let seed = if self.deterministic {
+            self.seed
+        } else {
+            randi()
+        };
+        self.get_boids_singleton().bind_mut().set_seed(seed);
     }
 
     #[inline(always)]
-    fn physics_process(&mut self, _: f64) {
-        if self.get_engine_singleton().get_physics_frames() % (self.process_per_tick as u64) == 0 {
+    fn physics_process(&mut self, delta: f64) {
+        if self
+            .get_engine_singleton()
+            .get_physics_frames()
+            .is_multiple_of(self.process_per_tick as u64)
+        {
             let (process_2d, process_3d) = (self.process_2d, self.process_3d);
+            // we only process every N ticks, so a single step needs to cover all of them
+            let delta = delta * self.process_per_tick as f64;
             let mut s = self.get_boids_singleton().bind_mut();
             if process_2d {
-                s.process_boids_2d();
+                s.process_boids_2d(delta);
             }
             if process_3d {
-                s.process_boids_3d();
+                s.process_boids_3d(delta);
             }
         }
     }
@@ -122,54 +144,216 @@ impl INode for BoidsProcess {
 #[class(init, base=Object)]
 /// Singleton that holds all boids and flocks and manages them.
 struct Boids {
-    flocks2d: FxIndexMap<InstanceId, Gd<Flock2D>>,
-    boids2d: FxIndexMap<InstanceId, Gd<Boid2D>>,
-    flocks3d: FxIndexMap<InstanceId, Gd<Flock3D>>,
-    boids3d: FxIndexMap<InstanceId, Gd<Boid3D>>,
+    world2d: FlockWorld<Flock2D, Boid2D>,
+    world3d: FlockWorld<Flock3D, Boid3D>,
+    obstacles2d: FxIndexMap<InstanceId, Gd<BoidObstacle2D>>,
+    obstacles3d: FxIndexMap<InstanceId, Gd<BoidObstacle3D>>,
+    flow_fields2d: FxIndexMap<InstanceId, Gd<BoidFlowField2D>>,
+    flow_fields3d: FxIndexMap<InstanceId, Gd<BoidFlowField3D>>,
+    swarms2d: FxIndexMap<InstanceId, Gd<BoidSwarm2D>>,
+    swarms3d: FxIndexMap<InstanceId, Gd<BoidSwarm3D>>,
+    /// Seeds the generators of new flocks and swarms, in the order they're registered.
+    rng: boids_core::Rng,
     base: Base<Object>,
 }
 
 impl Boids {
     fn register_flock_2d(&mut self, flock_id: InstanceId) {
-        let flock = Gd::from_instance_id(flock_id);
-        self.flocks2d.insert(flock_id, flock);
+        let rng = self.rng.fork();
+        self.world2d.register_flock(flock_id, rng);
         godot_print!("[Boids] flock {flock_id} registered");
     }
 
     fn unregister_flock_2d(&mut self, flock_id: InstanceId) {
-        self.flocks2d.shift_remove(&flock_id);
+        self.world2d.unregister_flock(flock_id);
         godot_print!("[Boids] flock {flock_id} unregistered");
     }
 
     #[inline(always)]
-    fn register_boid_2d(&mut self, boid_id: InstanceId, boid: Gd<Boid2D>) {
-        self.boids2d.insert(boid_id, boid);
+    fn register_boid_2d(
+        &mut self,
+        flock_id: InstanceId,
+        boid_id: InstanceId,
+        boid: boids_core::Boid,
+        properties: Option<Gd<BoidProperties>>,
+    ) {
+        self.world2d
+            .register_boid(flock_id, boid_id, boid, properties);
+    }
+
+    #[inline(always)]
+    fn unregister_boid_2d(
+        &mut self,
+        flock_id: InstanceId,
+        boid_id: InstanceId,
+    ) -> Option<boids_core::Boid> {
+        self.world2d.unregister_boid(flock_id, boid_id)
+    }
+
+    #[inline(always)]
+    fn get_boid_velocity_2d(&self, flock_id: InstanceId, boid_id: InstanceId) -> Option<Vec3> {
+        self.world2d.get_boid_velocity(flock_id, boid_id)
+    }
+
+    #[inline(always)]
+    fn set_boid_velocity_2d(&mut self, flock_id: InstanceId, boid_id: InstanceId, velocity: Vec3) {
+        self.world2d.set_boid_velocity(flock_id, boid_id, velocity);
     }
 
     #[inline(always)]
-    fn unregister_boid_2d(&mut self, boid_id: InstanceId) {
-        self.boids2d.shift_remove(&boid_id);
+    fn set_boid_properties_2d(
+        &mut self,
+        flock_id: InstanceId,
+        boid_id: InstanceId,
+        properties: Option<Gd<BoidProperties>>,
+    ) {
+        self.world2d
+            .set_boid_properties(flock_id, boid_id, properties);
+    }
+
+    #[inline(always)]
+    fn set_boid_orientation_2d(
+        &mut self,
+        flock_id: InstanceId,
+        boid_id: InstanceId,
+        orientation: Option<boids_core::Orientation>,
+    ) {
+        self.world2d
+            .set_boid_orientation(flock_id, boid_id, orientation);
     }
 
     fn register_flock_3d(&mut self, flock_id: InstanceId) {
-        let flock = Gd::from_instance_id(flock_id);
-        self.flocks3d.insert(flock_id, flock);
+        let rng = self.rng.fork();
+        self.world3d.register_flock(flock_id, rng);
         godot_print!("[Boids] flock {flock_id} registered");
     }
 
     fn unregister_flock_3d(&mut self, flock_id: InstanceId) {
-        self.flocks3d.shift_remove(&flock_id);
+        self.world3d.unregister_flock(flock_id);
         godot_print!("[Boids] flock {flock_id} unregistered");
     }
 
     #[inline(always)]
-    fn register_boid_3d(&mut self, boid_id: InstanceId, boid: Gd<Boid3D>) {
-        self.boids3d.insert(boid_id, boid);
+    fn register_boid_3d(
+        &mut self,
+        flock_id: InstanceId,
+        boid_id: InstanceId,
+        boid: boids_core::Boid,
+        properties: Option<Gd<BoidProperties>>,
+    ) {
+        self.world3d
+            .register_boid(flock_id, boid_id, boid, properties);
+    }
+
+    #[inline(always)]
+    fn unregister_boid_3d(
+        &mut self,
+        flock_id: InstanceId,
+        boid_id: InstanceId,
+    ) -> Option<boids_core::Boid> {
+        self.world3d.unregister_boid(flock_id, boid_id)
+    }
+
+    #[inline(always)]
+    fn get_boid_velocity_3d(&self, flock_id: InstanceId, boid_id: InstanceId) -> Option<Vec3> {
+        self.world3d.get_boid_velocity(flock_id, boid_id)
+    }
+
+    #[inline(always)]
+    fn set_boid_velocity_3d(&mut self, flock_id: InstanceId, boid_id: InstanceId, velocity: Vec3) {
+        self.world3d.set_boid_velocity(flock_id, boid_id, velocity);
     }
 
     #[inline(always)]
-    fn unregister_boid_3d(&mut self, boid_id: InstanceId) {
-        self.boids3d.shift_remove(&boid_id);
+    fn set_boid_properties_3d(
+        &mut self,
+        flock_id: InstanceId,
+        boid_id: InstanceId,
+        properties: Option<Gd<BoidProperties>>,
+    ) {
+        self.world3d
+            .set_boid_properties(flock_id, boid_id, properties);
+    }
+
+    #[inline(always)]
+    fn set_boid_orientation_3d(
+        &mut self,
+        flock_id: InstanceId,
+        boid_id: InstanceId,
+        orientation: Option<boids_core::Orientation>,
+    ) {
+        self.world3d
+            .set_boid_orientation(flock_id, boid_id, orientation);
+    }
+
+    fn register_obstacle_2d(&mut self, obstacle_id: InstanceId) {
+        let obstacle = Gd::from_instance_id(obstacle_id);
+        self.obstacles2d.insert(obstacle_id, obstacle);
+        godot_print!("[Boids] obstacle {obstacle_id} registered");
+    }
+
+    fn unregister_obstacle_2d(&mut self, obstacle_id: InstanceId) {
+        self.obstacles2d.shift_remove(&obstacle_id);
+        godot_print!("[Boids] obstacle {obstacle_id} unregistered");
+    }
+
+    fn register_obstacle_3d(&mut self, obstacle_id: InstanceId) {
+        let obstacle = Gd::from_instance_id(obstacle_id);
+        self.obstacles3d.insert(obstacle_id, obstacle);
+        godot_print!("[Boids] obstacle {obstacle_id} registered");
+    }
+
+    fn unregister_obstacle_3d(&mut self, obstacle_id: InstanceId) {
+        self.obstacles3d.shift_remove(&obstacle_id);
+        godot_print!("[Boids] obstacle {obstacle_id} unregistered");
+    }
+
+    fn register_flow_field_2d(&mut self, flow_field_id: InstanceId) {
+        let flow_field = Gd::from_instance_id(flow_field_id);
+        self.flow_fields2d.insert(flow_field_id, flow_field);
+        godot_print!("[Boids] flow field {flow_field_id} registered");
+    }
+
+    fn unregister_flow_field_2d(&mut self, flow_field_id: InstanceId) {
+        self.flow_fields2d.shift_remove(&flow_field_id);
+        godot_print!("[Boids] flow field {flow_field_id} unregistered");
+    }
+
+    fn register_flow_field_3d(&mut self, flow_field_id: InstanceId) {
+        let flow_field = Gd::from_instance_id(flow_field_id);
+        self.flow_fields3d.insert(flow_field_id, flow_field);
+        godot_print!("[Boids] flow field {flow_field_id} registered");
+    }
+
+    fn unregister_flow_field_3d(&mut self, flow_field_id: InstanceId) {
+        self.flow_fields3d.shift_remove(&flow_field_id);
+        godot_print!("[Boids] flow field {flow_field_id} unregistered");
+    }
+
+    /// Registers a swarm, returning the generator it spawns and wanders with.
+    fn register_swarm_2d(&mut self, swarm_id: InstanceId) -> boids_core::Rng {
+        let swarm = Gd::from_instance_id(swarm_id);
+        self.swarms2d.insert(swarm_id, swarm);
+        godot_print!("[Boids] swarm {swarm_id} registered");
+        self.rng.fork()
+    }
+
+    fn unregister_swarm_2d(&mut self, swarm_id: InstanceId) {
+        self.swarms2d.shift_remove(&swarm_id);
+        godot_print!("[Boids] swarm {swarm_id} unregistered");
+    }
+
+    /// Registers a swarm, returning the generator it spawns and wanders with.
+    fn register_swarm_3d(&mut self, swarm_id: InstanceId) -> boids_core::Rng {
+        let swarm = Gd::from_instance_id(swarm_id);
+        self.swarms3d.insert(swarm_id, swarm);
+        godot_print!("[Boids] swarm {swarm_id} registered");
+        self.rng.fork()
+    }
+
+    fn unregister_swarm_3d(&mut self, swarm_id: InstanceId) {
+        self.swarms3d.shift_remove(&swarm_id);
+        godot_print!("[Boids] swarm {swarm_id} unregistered");
     }
 }
 
@@ -177,130 +361,173 @@ impl Boids {
 impl Boids {
     #[func]
     #[inline(always)]
-    /// Process all 2D boids once.
+    /// Process all 2D boids once, advancing them by `delta` seconds.
     /// NOTE: This function is not intended to be manually called. Prefer using `BoidsProcess` as an autoload singleton where possible.
-    fn process_boids_2d(&mut self) {
-        process_boids(&mut self.boids2d, &self.flocks2d)
+    fn process_boids_2d(&mut self, delta: f64) {
+        self.world2d
+            .process(&self.obstacles2d, &mut self.flow_fields2d, delta as f32);
+        for swarm in self.swarms2d.values_mut() {
+            swarm
+                .bind_mut()
+                .process(self.world2d.environment(), delta as f32);
+        }
     }
 
     #[func]
     #[inline(always)]
-    /// Process all 3D boids once.
+    /// Process all 3D boids once, advancing them by `delta` seconds.
     /// NOTE: This function is not intended to be manually called. Prefer using `BoidsProcess` as an autoload singleton where possible.
-    fn process_boids_3d(&mut self) {
-        process_boids(&mut self.boids3d, &self.flocks3d)
+    fn process_boids_3d(&mut self, delta: f64) {
+        self.world3d
+            .process(&self.obstacles3d, &mut self.flow_fields3d, delta as f32);
+        for swarm in self.swarms3d.values_mut() {
+            swarm
+                .bind_mut()
+                .process(self.world3d.environment(), delta as f32);
+        }
+    }
+
+    #[func]
+    /// Seed all randomness (wandering and spawning swarm boids) from `seed`, so that the same scene plays out the same way every run.
+    /// Flocks and swarms are reseeded in the order they were registered. Call this before swarms are ready, as that's when they spawn their boids.
+    /// Results only match across platforms if the extension is built without the `fast-math` feature.
+    fn set_seed(&mut self, seed: i64) {
+        self.rng = boids_core::Rng::new(seed as u64);
+        self.world2d.reseed(&mut self.rng);
+        self.world3d.reseed(&mut self.rng);
+        for swarm in self.swarms2d.values_mut() {
+            swarm.bind_mut().set_rng(self.rng.fork());
+        }
+        for swarm in self.swarms3d.values_mut() {
+            swarm.bind_mut().set_rng(self.rng.fork());
+        }
+    }
+
+    #[func]
+    /// Set how the boids of `flock` react to the boids of `other`, noticing them within `radius`.
+    /// Both flocks have to be `Flock2D`s, or both `Flock3D`s. Relations are one way, so `other` can react differently (eg. flee from a chasing `flock`).
+    /// Set the mode to `Ignore` to remove a relation. Relations are forgotten when either flock leaves the tree.
+    fn set_flock_relation(
+        &mut self,
+        flock: Gd<Node>,
+        other: Gd<Node>,
+        mode: RelationMode,
+        radius: f32,
+        weight: f32,
+    ) {
+        let (flock_id, other_id) = (flock.instance_id(), other.instance_id());
+        if radius.is_nan() || radius < 0.0 {
+            godot_error!("[Boids] relation radius of {flock_id} to {other_id} can't be {radius}");
+            return;
+        }
+        let relation = boids_core::Relation::new(0, 0, mode.into())
+            .with_radius(radius)
+            .with_weight(weight);
+        let is_2d = |node: &Gd<Node>| node.clone().try_cast::<Flock2D>().is_ok();
+        let is_3d = |node: &Gd<Node>| node.clone().try_cast::<Flock3D>().is_ok();
+        if is_2d(&flock) && is_2d(&other) {
+            self.world2d.set_relation(flock_id, other_id, relation);
+        } else if is_3d(&flock) && is_3d(&other) {
+            self.world3d.set_relation(flock_id, other_id, relation);
+        } else {
+            godot_error!("[Boids] {flock_id} and {other_id} aren't both Flock2Ds or both Flock3Ds");
+        }
     }
 
     #[func]
     #[inline(always)]
     /// Gets the total 2D boid count.
     fn get_total_boid_2d_count(&self) -> i64 {
-        self.boids2d.len() as i64
+        self.world2d.boid_count() as i64
     }
 
     #[func]
     #[inline(always)]
     /// Gets the total 2D flock count.
     fn get_total_flock_2d_count(&self) -> i64 {
-        self.flocks2d.len() as i64
+        self.world2d.flock_count() as i64
     }
 
     #[func]
     #[inline(always)]
     /// Gets the total 3D boid count.
     fn get_total_boid_3d_count(&self) -> i64 {
-        self.boids3d.len() as i64
+        self.world3d.boid_count() as i64
     }
 
     #[func]
     #[inline(always)]
     /// Gets the total 3D flock count.
     fn get_total_flock_3d_count(&self) -> i64 {
-        self.flocks3d.len() as i64
+        self.world3d.flock_count() as i64
+    }
+
+    #[func]
+    #[inline(always)]
+    /// Gets the total 2D obstacle count.
+    fn get_total_obstacle_2d_count(&self) -> i64 {
+        self.obstacles2d.len() as i64
+    }
+
+    #[func]
+    #[inline(always)]
+    /// Gets the total 3D obstacle count.
+    fn get_total_obstacle_3d_count(&self) -> i64 {
+        self.obstacles3d.len() as i64
+    }
+
+    #[func]
+    #[inline(always)]
+    /// Gets the total 2D flow field count.
+    fn get_total_flow_field_2d_count(&self) -> i64 {
+        self.flow_fields2d.len() as i64
+    }
+
+    #[func]
+    #[inline(always)]
+    /// Gets the total 3D flow field count.
+    fn get_total_flow_field_3d_count(&self) -> i64 {
+        self.flow_fields3d.len() as i64
+    }
+
+    #[func]
+    #[inline(always)]
+    /// Gets the total 2D swarm count.
+    fn get_total_swarm_2d_count(&self) -> i64 {
+        self.swarms2d.len() as i64
+    }
+
+    #[func]
+    #[inline(always)]
+    /// Gets the total 3D swarm count.
+    fn get_total_swarm_3d_count(&self) -> i64 {
+        self.swarms3d.len() as i64
     }
 }
 
 #[inline(always)]
-const fn to_glam_vec(godot_vec: Vector3) -> Vec3 {
-    vec3(godot_vec.x, godot_vec.y, godot_vec.z)
+fn to_glam_affine_2d(transform: Transform2D) -> Affine3A {
+    let Transform2D { a, b, origin } = transform;
+    Affine3A::from_cols(
+        vec3a(a.x, a.y, 0.0),
+        vec3a(b.x, b.y, 0.0),
+        Vec3A::Z,
+        vec3a(origin.x, origin.y, 0.0),
+    )
 }
 
 #[inline(always)]
-fn process_boids<F, B>(
-    boids: &mut FxIndexMap<InstanceId, Gd<B>>,
-    flocks: &FxIndexMap<InstanceId, Gd<F>>,
-) where
-    F: Flock + GodotClass,
-    F: Bounds<Declarer = DeclUser>,
-    B: Boid + GodotClass,
-    B: Bounds<Declarer = DeclUser>,
-{
-    #[cfg(feature = "stats")]
-    let time = std::time::Instant::now();
-    let total_boid_count = boids.len();
-    let mut calc_funcs = Vec::with_capacity(total_boid_count);
-    for (_, flock) in flocks.iter() {
-        let flock = flock.bind();
-        let flock_props = flock.get_flock_properties();
-        let target_position = flock.get_target_position();
-        let boids = Arc::new(flock.get_boids_posvel());
-        for (boid_id, (boid_pos, boid_vel, boid_props)) in flock.get_boids() {
-            let boid_id = *boid_id;
-            let flock_props = flock_props.clone();
-            let target_position = target_position.clone();
-            let boids = boids.clone();
-            calc_funcs.push((boid_id, move || {
-                boid::calculate_boid(
-                    boid_pos,
-                    boid_vel,
-                    boid_props,
-                    flock_props,
-                    boids,
-                    target_position,
-                )
-            }));
-        }
-    }
-    #[cfg(feature = "stats")]
-    godot_print!(
-        "[Boids] preparing all calculations took {} micros",
-        time.elapsed().as_micros()
-    );
-
-    #[cfg(feature = "stats")]
-    let time = std::time::Instant::now();
-    let forces: Vec<(InstanceId, Vec3)> = calc_funcs
-        .into_par_iter()
-        .fold(
-            || Vec::<(InstanceId, Vec3)>::with_capacity(total_boid_count),
-            |mut acc, (boid_id, calc_fn)| {
-                let force = calc_fn();
-                acc.push((boid_id, force));
-                acc
-            },
-        )
-        .reduce(
-            || Vec::<(InstanceId, Vec3)>::with_capacity(total_boid_count),
-            |mut left, mut right| {
-                left.append(&mut right);
-                left
-            },
-        );
-    #[cfg(feature = "stats")]
-    godot_print!(
-        "[Boids] calculating all boids took {} micros",
-        time.elapsed().as_micros()
-    );
-
-    #[cfg(feature = "stats")]
-    let time = std::time::Instant::now();
-    for (boid_id, force) in forces {
-        let boid = unsafe { boids.get_mut(&boid_id).unwrap_unchecked() };
-        boid.bind_mut().apply_force(force);
-    }
-    #[cfg(feature = "stats")]
-    godot_print!(
-        "[Boids] applying forces took {} micros",
-        time.elapsed().as_micros()
-    );
+fn to_glam_affine_3d(transform: Transform3D) -> Affine3A {
+    let Transform3D { basis, origin } = transform;
+    Affine3A::from_cols(
+        to_glam_vec(basis.col_a()).into(),
+        to_glam_vec(basis.col_b()).into(),
+        to_glam_vec(basis.col_c()).into(),
+        to_glam_vec(origin).into(),
+    )
+}
+
+#[inline(always)]
+const fn to_glam_vec(godot_vec: Vector3) -> Vec3 {
+    vec3(godot_vec.x, godot_vec.y, godot_vec.z)
 }
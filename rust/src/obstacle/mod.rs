@@ -0,0 +1,9 @@
+pub mod obstacle_2d;
+pub mod obstacle_3d;
+
+pub use obstacle_2d::*;
+pub use obstacle_3d::*;
+
+pub trait Obstacle {
+    fn to_core_obstacle(&self) -> boids_core::Obstacle;
+}
@@ -0,0 +1,97 @@
+use glam::*;
+use godot::prelude::*;
+
+use crate::get_singleton;
+
+use super::Obstacle;
+
+#[derive(GodotConvert, Var, Export, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+/// The shape of a `BoidObstacle2D`.
+pub enum ObstacleShape2D {
+    #[default]
+    /// A single point.
+    Point,
+    /// A circle, with the obstacle's `radius`. Follows the scale of the node, using its largest axis.
+    Circle,
+    /// A rectangle, with the obstacle's `size`. Follows the rotation and scale of the node.
+    Rect,
+}
+
+#[derive(GodotClass)]
+#[class(init, base=Node2D)]
+/// A 2D obstacle that boids will steer away from.
+/// Boids start avoiding it once they are closer than `margin` to its edge, how much they avoid it depends on their `avoidance` property.
+pub struct BoidObstacle2D {
+    #[export]
+    /// The shape of this obstacle.
+    shape: ObstacleShape2D,
+    #[export]
+    #[init(val = 32.0)]
+    /// Radius of the obstacle, if its shape is a circle.
+    radius: f32,
+    #[export]
+    #[init(val = Vector2::new(64.0, 64.0))]
+    /// Size of the obstacle, if its shape is a rectangle.
+    size: Vector2,
+    #[export]
+    #[init(val = 64.0)]
+    /// Distance from the edge of the obstacle at which boids start avoiding it.
+    margin: f32,
+    #[export]
+    /// If enabled, boids are kept inside of the obstacle instead, avoiding its edges from the inside.
+    /// Useful for keeping a flock inside an area.
+    inverted: bool,
+    base: Base<Node2D>,
+}
+
+#[godot_api]
+impl INode2D for BoidObstacle2D {
+    fn enter_tree(&mut self) {
+        get_singleton()
+            .bind_mut()
+            .register_obstacle_2d(self.get_id())
+    }
+
+    fn exit_tree(&mut self) {
+        get_singleton()
+            .bind_mut()
+            .unregister_obstacle_2d(self.get_id())
+    }
+}
+
+#[godot_api]
+impl BoidObstacle2D {
+    #[func]
+    #[inline(always)]
+    /// Retrieve the ID of this obstacle.
+    pub fn get_id(&self) -> InstanceId {
+        self.base().instance_id()
+    }
+}
+
+impl Obstacle for BoidObstacle2D {
+    #[inline(always)]
+    fn to_core_obstacle(&self) -> boids_core::Obstacle {
+        let pos = self.base().get_global_position();
+        let shape = match self.shape {
+            ObstacleShape2D::Point => boids_core::ObstacleShape::Point,
+            ObstacleShape2D::Circle => {
+                let scale = self.base().get_global_scale().abs();
+                boids_core::ObstacleShape::Sphere {
+                    radius: self.radius * scale.x.max(scale.y),
+                }
+            }
+            ObstacleShape2D::Rect => {
+                let size = self.size * self.base().get_global_scale().abs();
+                boids_core::ObstacleShape::Box {
+                    half_extents: vec3(size.x * 0.5, size.y * 0.5, f32::INFINITY),
+                }
+            }
+        };
+        let rotation = Quat::from_rotation_z(self.base().get_global_rotation());
+        boids_core::Obstacle::new(vec3(pos.x, pos.y, 0.0), shape, self.margin)
+            .with_rotation(rotation)
+            .inverted(self.inverted)
+    }
+}
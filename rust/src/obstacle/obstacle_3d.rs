@@ -0,0 +1,92 @@
+use glam::*;
+use godot::prelude::*;
+
+use crate::{get_singleton, to_glam_vec};
+
+use super::Obstacle;
+
+#[derive(GodotConvert, Var, Export, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+/// The shape of a `BoidObstacle3D`.
+pub enum ObstacleShape3D {
+    #[default]
+    /// A single point.
+    Point,
+    /// A sphere, with the obstacle's `radius`. Follows the scale of the node, using its largest axis.
+    Sphere,
+    /// A box, with the obstacle's `size`. Follows the rotation and scale of the node.
+    Box,
+}
+
+#[derive(GodotClass)]
+#[class(init, base=Node3D)]
+/// A 3D obstacle that boids will steer away from.
+/// Boids start avoiding it once they are closer than `margin` to its surface, how much they avoid it depends on their `avoidance` property.
+pub struct BoidObstacle3D {
+    #[export]
+    /// The shape of this obstacle.
+    shape: ObstacleShape3D,
+    #[export]
+    #[init(val = 1.0)]
+    /// Radius of the obstacle, if its shape is a sphere.
+    radius: f32,
+    #[export]
+    #[init(val = Vector3::new(2.0, 2.0, 2.0))]
+    /// Size of the obstacle, if its shape is a box.
+    size: Vector3,
+    #[export]
+    #[init(val = 2.0)]
+    /// Distance from the surface of the obstacle at which boids start avoiding it.
+    margin: f32,
+    #[export]
+    /// If enabled, boids are kept inside of the obstacle instead, avoiding its surface from the inside.
+    /// Useful for keeping a flock inside an area.
+    inverted: bool,
+    base: Base<Node3D>,
+}
+
+#[godot_api]
+impl INode3D for BoidObstacle3D {
+    fn enter_tree(&mut self) {
+        get_singleton()
+            .bind_mut()
+            .register_obstacle_3d(self.get_id())
+    }
+
+    fn exit_tree(&mut self) {
+        get_singleton()
+            .bind_mut()
+            .unregister_obstacle_3d(self.get_id())
+    }
+}
+
+#[godot_api]
+impl BoidObstacle3D {
+    #[func]
+    #[inline(always)]
+    /// Retrieve the ID of this obstacle.
+    pub fn get_id(&self) -> InstanceId {
+        self.base().instance_id()
+    }
+}
+
+impl Obstacle for BoidObstacle3D {
+    #[inline(always)]
+    fn to_core_obstacle(&self) -> boids_core::Obstacle {
+        let transform = self.base().get_global_transform();
+        let shape = match self.shape {
+            ObstacleShape3D::Point => boids_core::ObstacleShape::Point,
+            ObstacleShape3D::Sphere => boids_core::ObstacleShape::Sphere {
+                radius: self.radius * to_glam_vec(transform.basis.scale()).abs().max_element(),
+            },
+            ObstacleShape3D::Box => boids_core::ObstacleShape::Box {
+                half_extents: to_glam_vec(self.size * transform.basis.scale()).abs() * 0.5,
+            },
+        };
+        let rotation = transform.basis.orthonormalized().to_quat();
+        let rotation = Quat::from_xyzw(rotation.x, rotation.y, rotation.z, rotation.w);
+        boids_core::Obstacle::new(to_glam_vec(transform.origin), shape, self.margin)
+            .with_rotation(rotation)
+            .inverted(self.inverted)
+    }
+}
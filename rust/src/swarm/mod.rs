@@ -0,0 +1,47 @@
+use glam::*;
+
+pub mod swarm_2d;
+pub mod swarm_3d;
+
+pub use swarm_2d::*;
+pub use swarm_3d::*;
+
+/// Resizes the boids of `flock` to `count`, spawning new boids at random positions within `extents` (centered on the origin),
+/// moving in a random direction at `speed`. Axes with no extent (eg. Z for 2D swarms) are left at zero.
+/// Positions come from the rng of the flock, so swarms spawn the same way for the same seed.
+fn resize_swarm(flock: &mut boids_core::Flock, count: usize, extents: Vec3, speed: f32) {
+    let axes = Vec3::select(extents.cmpeq(Vec3::ZERO), Vec3::ZERO, Vec3::ONE);
+    let boids_core::Flock {
+        boids,
+        rng,
+        dimensions,
+        ..
+    } = flock;
+    boids.truncate(count);
+    while boids.len() < count {
+        let position = rng.next_vec3(*dimensions) * extents * 0.5;
+        let direction = rng.next_vec3(*dimensions) * axes;
+        let velocity = direction.normalize_or_zero() * speed;
+        boids.push(boids_core::Boid::new(position, velocity, 0));
+    }
+}
+
+/// Rotation of a 2D boid moving with `velocity`, facing it with its +X axis like `Node2D.look_at`.
+/// Boids that aren't moving keep facing +X.
+#[inline(always)]
+fn facing_2d(velocity: Vec2) -> Mat2 {
+    let forward = velocity.normalize_or(Vec2::X);
+    Mat2::from_cols(forward, forward.perp())
+}
+
+/// Rotation of a 3D boid moving with `velocity`, facing it with its -Z axis like `Node3D.look_at`.
+/// Boids that aren't moving, or are moving straight up or down, keep facing -Z.
+#[inline(always)]
+fn facing_3d(velocity: Vec3) -> Mat3 {
+    let back = -velocity.normalize_or_zero();
+    let right = Vec3::Y.cross(back).normalize_or_zero();
+    if right == Vec3::ZERO {
+        return Mat3::IDENTITY;
+    }
+    Mat3::from_cols(right, back.cross(right), back)
+}
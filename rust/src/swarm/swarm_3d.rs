@@ -0,0 +1,273 @@
+use glam::*;
+use godot::{
+    classes::{multi_mesh::TransformFormat, IMultiMeshInstance3D, MultiMesh, MultiMeshInstance3D},
+    prelude::*,
+};
+
+use crate::{
+    get_singleton, to_glam_affine_3d, to_glam_vec, BoidProperties, BoidTarget3D, BoundsMode, Flock,
+    FlockProperties, Partitioning, TargetMode,
+};
+
+use super::{facing_3d, resize_swarm};
+
+#[derive(GodotClass)]
+#[class(init, base=MultiMeshInstance3D)]
+/// A 3D flock of boids that aren't nodes, drawn with the mesh of this node's `MultiMesh`.
+/// Use this instead of `Flock3D` for thousands of boids that don't need to be nodes.
+/// Boids avoid `BoidObstacle3D`s, but raycast avoidance isn't supported.
+pub struct BoidSwarm3D {
+    #[export]
+    #[var(get, set = set_count)]
+    #[init(val = 100)]
+    /// How many boids are in this swarm.
+    count: i64,
+    #[export]
+    #[init(val = Vector3::new(10.0, 10.0, 10.0))]
+    /// Size of the box (centered on this node) boids are spawned in.
+    spawn_extents: Vector3,
+    #[export]
+    /// Properties of this swarm.
+    /// Changes to the resource, or setting a different one, apply from the next physics tick.
+    properties: Option<Gd<FlockProperties>>,
+    #[export]
+    /// Properties of every boid in this swarm.
+    /// Changes to the resource, or setting a different one, apply from the next physics tick.
+    boid_properties: Option<Gd<BoidProperties>>,
+    #[export]
+    /// A target node for the swarm to follow.
+    target: Option<Gd<Node3D>>,
+    #[export]
+    /// How the swarm follows `target`. Use `Arrive` to have the swarm settle on it instead of circling around it.
+    target_mode: TargetMode,
+    #[export]
+    /// Additional targets for the swarm to follow or flee from, each with their own weight and mode.
+    targets: Array<Option<Gd<BoidTarget3D>>>,
+    #[export]
+    /// How boids in this swarm are partitioned to speed up finding neighbours.
+    /// `Tree` uses an octree, which can be better than `Grid` when boids are packed in a few dense clusters.
+    partitioning: Partitioning,
+    #[export]
+    /// How boids are kept inside of `bounds`.
+    bounds_mode: BoundsMode,
+    #[export]
+    #[init(val = Aabb::new(Vector3::new(-10.0, -10.0, -10.0), Vector3::new(20.0, 20.0, 20.0)))]
+    /// The box boids are kept inside of, relative to this node.
+    bounds: Aabb,
+    #[export]
+    #[init(val = 2.0)]
+    /// How close to the edges of `bounds` boids start steering back, with `BoundsMode.SteerBack`.
+    bounds_margin: f32,
+    #[init(val = boids_core::Flock::new_3d(Default::default()))]
+    flock: boids_core::Flock,
+    buffer: PackedFloat32Array,
+    base: Base<MultiMeshInstance3D>,
+}
+
+#[godot_api]
+impl IMultiMeshInstance3D for BoidSwarm3D {
+    fn enter_tree(&mut self) {
+        self.flock.rng = get_singleton().bind_mut().register_swarm_3d(self.get_id());
+    }
+
+    fn ready(&mut self) {
+        let mut multimesh = self.base().get_multimesh().unwrap_or_else(|| {
+            let multimesh = MultiMesh::new_gd();
+            self.base_mut().set_multimesh(&multimesh);
+            multimesh
+        });
+        // the transform format can only be changed while there are no instances
+        multimesh.set_instance_count(0);
+        multimesh.set_transform_format(TransformFormat::TRANSFORM_3D);
+        self.respawn();
+    }
+
+    fn exit_tree(&mut self) {
+        get_singleton()
+            .bind_mut()
+            .unregister_swarm_3d(self.get_id());
+    }
+}
+
+#[godot_api]
+impl BoidSwarm3D {
+    #[func]
+    #[inline(always)]
+    /// Retrieve the ID of this swarm.
+    pub fn get_id(&self) -> InstanceId {
+        self.base().instance_id()
+    }
+
+    #[func]
+    /// Set how many boids are in this swarm, spawning or removing boids as needed.
+    fn set_count(&mut self, count: i64) {
+        self.count = count.max(0);
+        if self.base().is_node_ready() {
+            self.respawn();
+        }
+    }
+
+    #[func]
+    /// Get the position of the boid at `index`, relative to this swarm.
+    fn get_boid_position(&self, index: i64) -> Vector3 {
+        let Some(pos) = self
+            .boid_index(index)
+            .map(|i| self.flock.boids.positions[i])
+        else {
+            return Vector3::ZERO;
+        };
+        Vector3::new(pos.x, pos.y, pos.z)
+    }
+
+    #[func]
+    /// Get the velocity of the boid at `index`.
+    fn get_boid_velocity(&self, index: i64) -> Vector3 {
+        let Some(vel) = self
+            .boid_index(index)
+            .map(|i| self.flock.boids.velocities[i])
+        else {
+            return Vector3::ZERO;
+        };
+        Vector3::new(vel.x, vel.y, vel.z)
+    }
+}
+
+impl BoidSwarm3D {
+    /// Checks that `index` is the index of a boid in this swarm.
+    fn boid_index(&self, index: i64) -> Option<usize> {
+        let count = self.flock.boids.len();
+        if index < 0 || index as usize >= count {
+            godot_error!("[BoidSwarm3D] boid index {index} is out of range (count is {count})");
+            return None;
+        }
+        Some(index as usize)
+    }
+
+    /// Replaces the generator this swarm wanders (and spawns boids) with.
+    pub(crate) fn set_rng(&mut self, rng: boids_core::Rng) {
+        self.flock.rng = rng;
+    }
+
+    fn respawn(&mut self) {
+        let count = self.count as usize;
+        let speed = self.get_core_boid_properties().max_speed;
+        let extents = to_glam_vec(self.spawn_extents);
+        resize_swarm(&mut self.flock, count, extents, speed);
+        if let Some(mut multimesh) = self.base().get_multimesh() {
+            multimesh.set_instance_count(count as i32);
+        }
+        self.buffer.resize(count * 12);
+        self.write_buffer();
+    }
+
+    #[inline(always)]
+    fn get_core_boid_properties(&self) -> boids_core::BoidProperties {
+        match self.boid_properties.as_ref() {
+            Some(props) => (&*props.bind()).into(),
+            None => boids_core::BoidProperties::default(),
+        }
+    }
+
+    /// Advances the boids of this swarm by `delta` seconds, avoiding the obstacles in `environment`.
+    pub fn process(&mut self, environment: &boids_core::Environment, delta: f32) {
+        let mut flock = std::mem::replace(
+            &mut self.flock,
+            boids_core::Flock::new_3d(Default::default()),
+        );
+        self.update_core_flock(&mut flock);
+        flock.boid_properties.clear();
+        flock.boid_properties.push(self.get_core_boid_properties());
+        flock.step(environment, delta);
+        self.flock = flock;
+        self.write_buffer();
+    }
+
+    /// Writes the transforms of all boids into the `MultiMesh` of this node.
+    fn write_buffer(&mut self) {
+        let Some(mut multimesh) = self.base().get_multimesh() else {
+            return;
+        };
+        let boids = &self.flock.boids;
+        let (transforms, _) = self.buffer.as_mut_slice().as_chunks_mut::<12>();
+        for ((transform, pos), vel) in transforms
+            .iter_mut()
+            .zip(&boids.positions)
+            .zip(&boids.velocities)
+        {
+            let Mat3 {
+                x_axis,
+                y_axis,
+                z_axis,
+            } = facing_3d(*vel);
+            // the buffer holds the rows of a 3x4 matrix
+            *transform = [
+                x_axis.x, y_axis.x, z_axis.x, pos.x, //
+                x_axis.y, y_axis.y, z_axis.y, pos.y, //
+                x_axis.z, y_axis.z, z_axis.z, pos.z,
+            ];
+        }
+        multimesh.set_buffer(&self.buffer);
+    }
+}
+
+impl Flock for BoidSwarm3D {
+    const DIMENSIONS: usize = 3;
+
+    #[inline(always)]
+    fn get_flock_properties(&self) -> boids_core::FlockProperties {
+        match self.properties.as_ref() {
+            Some(props) => (&*props.bind()).into(),
+            None => boids_core::FlockProperties::default(),
+        }
+    }
+
+    #[inline(always)]
+    fn get_targets(&self) -> Vec<boids_core::Target> {
+        let to_flock = self.base().get_global_transform().affine_inverse();
+        let target = self.target.as_ref().map(|t| {
+            boids_core::Target::new(
+                to_glam_vec(to_flock * t.get_global_position()),
+                self.target_mode.into(),
+            )
+        });
+        target
+            .into_iter()
+            .chain(
+                self.targets
+                    .iter_shared()
+                    .flatten()
+                    .map(|t| t.bind().to_core_target(to_flock)),
+            )
+            .collect()
+    }
+
+    #[inline(always)]
+    fn get_partitioning(&self) -> Partitioning {
+        self.partitioning
+    }
+
+    #[inline(always)]
+    fn get_global_affine(&self) -> Affine3A {
+        to_glam_affine_3d(self.base().get_global_transform())
+    }
+
+    #[inline(always)]
+    fn get_bounds(&self) -> Option<boids_core::Bounds> {
+        let mode = self.bounds_mode.to_core()?;
+        let (min, max) = (self.bounds.position, self.bounds.end());
+        Some(
+            boids_core::Bounds::new_3d(to_glam_vec(min), to_glam_vec(max), mode)
+                .with_margin(self.bounds_margin),
+        )
+    }
+
+    fn get_path(&mut self) -> Option<boids_core::Path> {
+        None
+    }
+
+    fn get_leader(&self) -> Option<(InstanceId, boids_core::Leader)> {
+        None
+    }
+
+    fn cast_avoidance_rays(&self, _boids: &mut boids_core::BoidStorage) {}
+}
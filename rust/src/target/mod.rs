@@ -0,0 +1,31 @@
+use godot::prelude::*;
+
+pub mod target_2d;
+pub mod target_3d;
+
+pub use target_2d::*;
+pub use target_3d::*;
+
+#[derive(GodotConvert, Var, Export, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[godot(via = i64)]
+/// How boids react to a target.
+pub enum TargetMode {
+    #[default]
+    /// Move towards the target.
+    Seek,
+    /// Move away from the target.
+    Flee,
+    /// Move towards the target, slowing down when getting close to it.
+    /// See `BoidProperties.arrive_slowing_radius` and `BoidProperties.arrive_stop_distance`.
+    Arrive,
+}
+
+impl From<TargetMode> for boids_core::TargetMode {
+    fn from(mode: TargetMode) -> Self {
+        match mode {
+            TargetMode::Seek => Self::Seek,
+            TargetMode::Flee => Self::Flee,
+            TargetMode::Arrive => Self::Arrive,
+        }
+    }
+}
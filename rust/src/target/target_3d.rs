@@ -0,0 +1,34 @@
+use godot::prelude::*;
+
+use crate::to_glam_vec;
+
+use super::TargetMode;
+
+#[derive(GodotClass)]
+#[class(init, base=Node3D)]
+/// A 3D target that flocks can follow or flee from, see `Flock3D.targets`.
+pub struct BoidTarget3D {
+    #[export]
+    /// How boids react to this target.
+    mode: TargetMode,
+    #[export]
+    #[init(val = 1.0)]
+    /// How strong this target is compared to the other targets of a flock.
+    weight: f32,
+    #[export]
+    /// Distance at which this target stops affecting boids, its force fades out until then.
+    /// Zero means boids are affected at any distance.
+    falloff_radius: f32,
+    base: Base<Node3D>,
+}
+
+impl BoidTarget3D {
+    /// Converts this target to a core target, with `to_flock` transforming from global space into the space of the flock.
+    #[inline(always)]
+    pub fn to_core_target(&self, to_flock: Transform3D) -> boids_core::Target {
+        let pos = to_flock * self.base().get_global_position();
+        boids_core::Target::new(to_glam_vec(pos), self.mode.into())
+            .with_weight(self.weight)
+            .with_falloff_radius(self.falloff_radius)
+    }
+}
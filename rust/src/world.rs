@@ -0,0 +1,323 @@
+use glam::*;
+use godot::{
+    obj::{bounds::DeclUser, Bounds},
+    prelude::*,
+};
+
+use crate::{Boid, BoidProperties, BoidPropertiesInterner, Flock, FlowField, FxIndexMap, Obstacle};
+
+/// Simulation state of all 2D or all 3D flocks.
+/// Boids are stored in `boids_core`'s contiguous arrays, their nodes only sync their positions in and out every tick.
+pub struct FlockWorld<F: GodotClass, B: GodotClass> {
+    /// Flock nodes, in the same order as the flocks of `world`.
+    flocks: FxIndexMap<InstanceId, Gd<F>>,
+    /// Boid nodes of every flock, in the same order as the boids of that flock.
+    boids: Vec<FxIndexMap<InstanceId, Gd<B>>>,
+    /// Properties resources of the boids of every flock, in the same order as the boids of that flock.
+    boid_properties: Vec<Vec<Option<Gd<BoidProperties>>>>,
+    /// How the boids of every flock turn to face their velocity, in the same order as the boids of that flock.
+    /// Set by the boid nodes whenever their settings change, so they don't need to be read every tick.
+    boid_orientations: Vec<Vec<Option<boids_core::Orientation>>>,
+    interner: BoidPropertiesInterner,
+    /// How flocks react to each other, by the IDs of the reacting and the other flock.
+    /// The flock indices and transform of the relations are filled in every tick.
+    relations: FxIndexMap<(InstanceId, InstanceId), boids_core::Relation>,
+    world: boids_core::World,
+}
+
+impl<F: GodotClass, B: GodotClass> Default for FlockWorld<F, B> {
+    fn default() -> Self {
+        Self {
+            flocks: FxIndexMap::default(),
+            boids: Vec::new(),
+            boid_properties: Vec::new(),
+            boid_orientations: Vec::new(),
+            interner: BoidPropertiesInterner::default(),
+            relations: FxIndexMap::default(),
+            world: boids_core::World::new(),
+        }
+    }
+}
+
+impl<F, B> FlockWorld<F, B>
+where
+    F: Flock + GodotClass,
+    F: Bounds<Declarer = DeclUser>,
+    B: Boid + GodotClass,
+{
+    #[inline(always)]
+    pub fn flock_count(&self) -> usize {
+        self.flocks.len()
+    }
+
+    #[inline(always)]
+    pub fn boid_count(&self) -> usize {
+        self.boids.iter().map(FxIndexMap::len).sum()
+    }
+
+    /// The obstacles boids avoided and the flow fields they went along with in the last `process` call.
+    #[inline(always)]
+    pub fn environment(&self) -> &boids_core::Environment {
+        &self.world.environment
+    }
+
+    /// Adds a flock, wandering with `rng`.
+    pub fn register_flock(&mut self, flock_id: InstanceId, rng: boids_core::Rng) {
+        if self.flocks.contains_key(&flock_id) {
+            return;
+        }
+        self.flocks.insert(flock_id, Gd::from_instance_id(flock_id));
+        self.boids.push(FxIndexMap::default());
+        self.boid_properties.push(Vec::new());
+        self.boid_orientations.push(Vec::new());
+        let props = boids_core::FlockProperties::default();
+        let mut core_flock = match F::DIMENSIONS {
+            2 => boids_core::Flock::new_2d(props),
+            _ => boids_core::Flock::new_3d(props),
+        };
+        core_flock.rng = rng;
+        self.world.flocks.push(core_flock);
+    }
+
+    pub fn unregister_flock(&mut self, flock_id: InstanceId) {
+        let Some((index, _, _)) = self.flocks.swap_remove_full(&flock_id) else {
+            return;
+        };
+        self.boids.swap_remove(index);
+        self.boid_properties.swap_remove(index);
+        self.boid_orientations.swap_remove(index);
+        self.world.flocks.swap_remove(index);
+        self.relations
+            .retain(|(flock, other), _| *flock != flock_id && *other != flock_id);
+    }
+
+    /// Gives every flock a new generator forked from `rng`, in the order they were registered.
+    pub fn reseed(&mut self, rng: &mut boids_core::Rng) {
+        for core_flock in &mut self.world.flocks {
+            core_flock.rng = rng.fork();
+        }
+    }
+
+    /// Sets how the boids of `flock_id` react to the boids of `other_id`, or removes the relation if it's `Ignore`.
+    pub fn set_relation(
+        &mut self,
+        flock_id: InstanceId,
+        other_id: InstanceId,
+        relation: boids_core::Relation,
+    ) {
+        if relation.mode == boids_core::RelationMode::Ignore {
+            self.relations.shift_remove(&(flock_id, other_id));
+        } else {
+            self.relations.insert((flock_id, other_id), relation);
+        }
+    }
+
+    /// Adds a boid to a flock. The `properties` index of `boid` is ignored, `properties` is used instead.
+    pub fn register_boid(
+        &mut self,
+        flock_id: InstanceId,
+        boid_id: InstanceId,
+        boid: boids_core::Boid,
+        properties: Option<Gd<BoidProperties>>,
+    ) {
+        let Some(index) = self.flocks.get_index_of(&flock_id) else {
+            godot_error!("[Boids] flock {flock_id} of boid {boid_id} isn't registered");
+            return;
+        };
+        let boids = &mut self.boids[index];
+        if boids.contains_key(&boid_id) {
+            return;
+        }
+        boids.insert(boid_id, Gd::from_instance_id(boid_id));
+        self.boid_properties[index].push(properties);
+        self.boid_orientations[index].push(None);
+        self.world.flocks[index].boids.push(boid);
+    }
+
+    /// Removes a boid from a flock, returning its last simulated state.
+    pub fn unregister_boid(
+        &mut self,
+        flock_id: InstanceId,
+        boid_id: InstanceId,
+    ) -> Option<boids_core::Boid> {
+        let flock_index = self.flocks.get_index_of(&flock_id)?;
+        let (index, _, _) = self.boids[flock_index].swap_remove_full(&boid_id)?;
+        self.boid_properties[flock_index].swap_remove(index);
+        self.boid_orientations[flock_index].swap_remove(index);
+        Some(self.world.flocks[flock_index].boids.swap_remove(index))
+    }
+
+    #[inline(always)]
+    fn boid_index(&self, flock_id: InstanceId, boid_id: InstanceId) -> Option<(usize, usize)> {
+        let flock_index = self.flocks.get_index_of(&flock_id)?;
+        let index = self.boids[flock_index].get_index_of(&boid_id)?;
+        Some((flock_index, index))
+    }
+
+    #[inline(always)]
+    pub fn get_boid_velocity(&self, flock_id: InstanceId, boid_id: InstanceId) -> Option<Vec3> {
+        let (flock_index, index) = self.boid_index(flock_id, boid_id)?;
+        Some(self.world.flocks[flock_index].boids.velocities[index])
+    }
+
+    #[inline(always)]
+    pub fn set_boid_velocity(&mut self, flock_id: InstanceId, boid_id: InstanceId, velocity: Vec3) {
+        if let Some((flock_index, index)) = self.boid_index(flock_id, boid_id) {
+            self.world.flocks[flock_index].boids.velocities[index] = velocity;
+        }
+    }
+
+    pub fn set_boid_properties(
+        &mut self,
+        flock_id: InstanceId,
+        boid_id: InstanceId,
+        properties: Option<Gd<BoidProperties>>,
+    ) {
+        if let Some((flock_index, index)) = self.boid_index(flock_id, boid_id) {
+            self.boid_properties[flock_index][index] = properties;
+        }
+    }
+
+    /// Sets how a boid turns to face its velocity, or `None` if it doesn't.
+    pub fn set_boid_orientation(
+        &mut self,
+        flock_id: InstanceId,
+        boid_id: InstanceId,
+        orientation: Option<boids_core::Orientation>,
+    ) {
+        if let Some((flock_index, index)) = self.boid_index(flock_id, boid_id) {
+            self.boid_orientations[flock_index][index] = orientation;
+        }
+    }
+
+    /// Advances all boids by `delta` seconds, avoiding `obstacles` and going along with `flow_fields`.
+    pub fn process<O, W>(
+        &mut self,
+        obstacles: &FxIndexMap<InstanceId, Gd<O>>,
+        flow_fields: &mut FxIndexMap<InstanceId, Gd<W>>,
+        delta: f32,
+    ) where
+        O: Obstacle + GodotClass,
+        O: Bounds<Declarer = DeclUser>,
+        W: FlowField + GodotClass,
+        W: Bounds<Declarer = DeclUser>,
+    {
+        #[cfg(feature = "stats")]
+        let time = std::time::Instant::now();
+        let environment = &mut self.world.environment;
+        environment.obstacles.clear();
+        environment.obstacles.extend(
+            obstacles
+                .values()
+                .map(|obstacle| obstacle.bind().to_core_obstacle()),
+        );
+        environment.flow_fields.clear();
+        environment.flow_fields.extend(
+            flow_fields
+                .values_mut()
+                .map(|flow_field| flow_field.bind_mut().to_core_flow_field()),
+        );
+        let mut to_global = Vec::with_capacity(self.flocks.len());
+        let flocks = self
+            .flocks
+            .values_mut()
+            .zip(&self.boids)
+            .zip(&self.boid_properties)
+            .zip(&mut self.world.flocks);
+        for (((flock, boids), boid_properties), core_flock) in flocks {
+            let flock_to_global = flock.bind().get_global_affine();
+            to_global.push(flock_to_global);
+            let storage = &core_flock.boids;
+            if !storage.is_empty() {
+                // where the boids were at the end of the last tick
+                let centroid = storage.positions.iter().sum::<Vec3>() / storage.len() as f32;
+                flock
+                    .bind_mut()
+                    .update_navigation(flock_to_global.transform_point3(centroid), delta);
+            }
+            let mut flock = flock.bind_mut();
+            flock.update_core_flock(core_flock);
+            core_flock.leader = flock
+                .get_leader()
+                .map(|(leader_id, leader)| boids_core::Leader {
+                    boid: boids.get_index_of(&leader_id),
+                    ..leader
+                });
+            let storage = &mut core_flock.boids;
+            let synced = storage.positions.iter_mut().zip(&mut storage.properties);
+            for ((boid, props), (position, properties)) in
+                boids.values().zip(boid_properties).zip(synced)
+            {
+                *position = B::get_boid_position(boid);
+                *properties = self.interner.intern(props.as_ref());
+            }
+            self.interner.drain_into(&mut core_flock.boid_properties);
+            storage.external_repulsion.fill(Vec3::ZERO);
+            flock.cast_avoidance_rays(storage);
+        }
+        self.world.relations.clear();
+        for ((flock_id, other_id), relation) in &self.relations {
+            let (Some(flock), Some(other)) = (
+                self.flocks.get_index_of(flock_id),
+                self.flocks.get_index_of(other_id),
+            ) else {
+                continue;
+            };
+            let to_flock = to_global[flock].inverse() * to_global[other];
+            self.world.relations.push(
+                boids_core::Relation::new(flock, other, relation.mode)
+                    .with_radius(relation.radius)
+                    .with_weight(relation.weight)
+                    .with_transform(to_flock),
+            );
+        }
+        #[cfg(feature = "stats")]
+        godot_print!(
+            "[Boids] preparing all calculations took {} micros",
+            time.elapsed().as_micros()
+        );
+
+        #[cfg(feature = "stats")]
+        let time = std::time::Instant::now();
+        self.world.step(delta);
+        #[cfg(feature = "stats")]
+        godot_print!(
+            "[Boids] calculating all boids took {} micros",
+            time.elapsed().as_micros()
+        );
+
+        #[cfg(feature = "stats")]
+        let time = std::time::Instant::now();
+        let flocks = self
+            .boids
+            .iter_mut()
+            .zip(&self.boid_orientations)
+            .zip(&self.world.flocks);
+        for ((boids, orientations), core_flock) in flocks {
+            let storage = &core_flock.boids;
+            let states = storage
+                .positions
+                .iter()
+                .zip(&storage.velocities)
+                .zip(&storage.accelerations)
+                .zip(orientations);
+            for (boid, (((position, velocity), acceleration), orientation)) in
+                boids.values_mut().zip(states)
+            {
+                B::set_boid_state(
+                    boid,
+                    *position,
+                    *velocity,
+                    *acceleration,
+                    *orientation,
+                    delta,
+                );
+            }
+        }
+        #[cfg(feature = "stats")]
+        godot_print!(
+            "[Boids] updating boids took {} micros",
+            time.elapsed().as_micros()
+        );
+    }
+}